@@ -1,5 +1,92 @@
 extern crate peg;
+extern crate serde_json;
+
+#[allow(dead_code)]
+#[path = "src/roblox/parser/binary.rs"]
+mod binary;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use serde_json::Value;
+use binary::Writer;
+
+const PINNED_API: &str = "api.json";
+
+fn of_type<'a>(blobs: &'a [Value], blob_type: &'a str) -> impl Iterator<Item = &'a Value> + 'a {
+    blobs.iter().filter(move |blob| blob["type"] == blob_type)
+}
+
+fn str_of<'a>(blob: &'a Value, key: &str) -> Result<&'a str, String> {
+    blob[key].as_str().ok_or_else(|| format!("{} has no {}: {}", PINNED_API, key, blob))
+}
+
+fn tags_of(blob: &Value) -> Vec<&str> {
+    blob["tags"].as_array()
+        .map(|tags| tags.iter().filter_map(|tag| tag.as_str()).collect())
+        .unwrap_or_default()
+}
+
+// Turns the pinned dump, in the flat format, into the snapshot the compiler
+// falls back to when it has no newer api.
+fn compile_api(text: &str) -> Result<Vec<u8>, String> {
+    let parsed: Value = serde_json::from_str(text).map_err(|e| format!("{} is not JSON: {}", PINNED_API, e))?;
+    let blobs = parsed.as_array().ok_or_else(|| format!("{} is not in the flat format", PINNED_API))?;
+
+    let enum_names = of_type(blobs, "Enum").map(|enm| str_of(enm, "Name")).collect::<Result<Vec<&str>, String>>()?;
+    let mut writer = Writer::new();
+
+    writer.u32(of_type(blobs, "Class").count() as u32);
+    for class in of_type(blobs, "Class") {
+        let name = str_of(class, "Name")?;
+        let props: Vec<&Value> = of_type(blobs, "Property").filter(|prop| prop["Class"] == name).collect();
+
+        writer.str(name);
+        // A missing or null superclass means none, as when the api is parsed
+        writer.str(class["Superclass"].as_str().unwrap_or(""));
+        writer.tags(&tags_of(class));
+        writer.u32(props.len() as u32);
+
+        for prop in props {
+            let (category, type_name) = binary::type_category(str_of(prop, "ValueType")?, |name| enum_names.contains(&name));
+
+            writer.str(str_of(prop, "Name")?);
+            writer.tags(&tags_of(prop));
+            writer.u8(category);
+            writer.str(type_name);
+        }
+    }
+
+    writer.u32(enum_names.len() as u32);
+    for enm in of_type(blobs, "Enum") {
+        let name = str_of(enm, "Name")?;
+        let items: Vec<&Value> = of_type(blobs, "EnumItem").filter(|item| item["Enum"] == name).collect();
+
+        writer.str(name);
+        writer.tags(&tags_of(enm));
+        writer.u32(items.len() as u32);
+
+        for item in items {
+            let value = item["Value"].as_u64().ok_or_else(|| format!("{} has no Value: {}", PINNED_API, item))?;
+
+            writer.str(str_of(item, "Name")?);
+            writer.u32(value as u32);
+        }
+    }
+
+    Ok(writer.into_bytes())
+}
 
 fn main() {
     peg::cargo_build("src/rg/rg_grammar.rustpeg");
-}
\ No newline at end of file
+
+    println!("cargo:rerun-if-changed={}", PINNED_API);
+    println!("cargo:rerun-if-changed=src/roblox/parser/binary.rs");
+
+    let text = fs::read_to_string(PINNED_API).expect("failed to read the pinned api");
+    let out_dir = env::var("OUT_DIR").unwrap();
+
+    let snapshot = compile_api(&text).unwrap_or_else(|e| panic!("failed to compile the pinned api: {}", e));
+
+    fs::write(Path::new(&out_dir).join("api.snapshot"), snapshot).expect("failed to write the api snapshot");
+}
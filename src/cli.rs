@@ -0,0 +1,231 @@
+use std::path::PathBuf;
+use codegen::Format;
+use roblox::ApiMode;
+use rg::diagnostics::MessageFormat;
+
+pub const USAGE: &str = "Usage:
+    rgss build <files|dirs>... [-o <out>] [--format lua|rbxmx|rbxm|json]
+    rgss watch <files|dirs>... [-o <out>] [--format lua|rbxmx|rbxm|json]
+    rgss check <files|dirs>...
+    rgss import <model.rbxmx|model.rbxm> [-o <out.rg>]
+    rgss fmt <files|dirs>... [--check]
+    rgss lsp
+
+Options:
+    --offline       Use the pinned or built in api, never a downloaded one
+    --update-api    Download the latest api before compiling
+    --message-format human|json
+                    Print diagnostics for people, or as one JSON object
+                    per line for editors and CI
+
+Directories are searched for .rg and .rgss files. Every stylesheet given
+applies to every .rg file. Without -o each output is written next to its
+source; with one source -o names the output file, otherwise a directory in
+which sources found in a directory keep their path within it.
+
+fmt rewrites .rg files in one layout, with --check it only lists the files
+which would change, failing if there are any. Properties are sorted when
+rgss.json sets \"fmt\": { \"sort_properties\": true }.
+lsp runs a language server over stdin and stdout for editors.
+
+The api is read from the file rgss.json pins, or else the newest dump in the
+user's cache, or else the one built into rgss. --offline skips the cache so
+only the pinned or built in api is used. Only --update-api downloads,
+fetching the dump of the current Roblox version into the cache.";
+
+#[derive(PartialEq, Debug)]
+pub enum Command {
+    Build { inputs: Vec<PathBuf>, output: Option<PathBuf>, format: Format },
+    Watch { inputs: Vec<PathBuf>, output: Option<PathBuf>, format: Format },
+    Check { inputs: Vec<PathBuf> },
+    Import { input: PathBuf, output: Option<PathBuf> },
+    Fmt { inputs: Vec<PathBuf>, check: bool },
+    Lsp,
+    Help,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Options {
+    pub command: Command,
+    pub api_mode: ApiMode,
+    pub message_format: MessageFormat,
+}
+
+// The arguments shared by every subcommand, before they are checked against it.
+struct Arguments {
+    inputs: Vec<PathBuf>,
+    output: Option<PathBuf>,
+    format: Option<Format>,
+    offline: bool,
+    update_api: bool,
+    check: bool,
+    message_format: MessageFormat,
+}
+
+fn parse_arguments(args: &[String]) -> Result<Arguments, String> {
+    let mut parsed = Arguments { inputs: vec![], output: None, format: None, offline: false, update_api: false, check: false,
+        message_format: MessageFormat::Human };
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next()
+            .ok_or_else(|| format!("{} expects a value", flag));
+
+        match arg.as_str() {
+            "-o" | "--output" => parsed.output = Some(PathBuf::from(value(arg)?)),
+            "-f" | "--format" => parsed.format = Some(Format::parse(value(arg)?)?),
+            "--offline" => parsed.offline = true,
+            "--update-api" => parsed.update_api = true,
+            "--check" => parsed.check = true,
+            "--message-format" => parsed.message_format = MessageFormat::parse(value(arg)?)?,
+            flag if flag.starts_with('-') && flag.len() > 1 => return Err(format!("Unknown option {}", flag)),
+            input => parsed.inputs.push(PathBuf::from(input)),
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn parse_command(subcommand: &str, arguments: Arguments) -> Result<Command, String> {
+    let Arguments { inputs, output, format, check, .. } = arguments;
+
+    if check && subcommand != "fmt" {
+        return Err("--check is only used by fmt".to_owned());
+    }
+
+    if subcommand == "lsp" {
+        return match (inputs.is_empty(), output, format) {
+            (true, None, None) => Ok(Command::Lsp),
+            _ => Err("lsp takes its files from the editor".to_owned()),
+        };
+    }
+
+    if inputs.is_empty() {
+        return Err(format!("{} expects at least one input", subcommand));
+    }
+
+    match subcommand {
+        "build" => Ok(Command::Build { inputs, output, format: format.unwrap_or(Format::Lua) }),
+        "watch" => Ok(Command::Watch { inputs, output, format: format.unwrap_or(Format::Lua) }),
+        "check" if output.is_some() || format.is_some() => Err("check does not write any output".to_owned()),
+        "check" => Ok(Command::Check { inputs }),
+        "import" if format.is_some() => Err("import always writes .rg source".to_owned()),
+        "import" if inputs.len() > 1 => Err("import expects a single model".to_owned()),
+        "fmt" if output.is_some() || format.is_some() => Err("fmt rewrites files in place".to_owned()),
+        "fmt" => Ok(Command::Fmt { inputs, check }),
+        "import" => Ok(Command::Import { input: inputs.into_iter().next().unwrap(), output }),
+        _ => Err(format!("Unknown command {}", subcommand)),
+    }
+}
+
+// Parses the arguments following the program name.
+pub fn parse(args: &[String]) -> Result<Options, String> {
+    let help = Options { command: Command::Help, api_mode: ApiMode::PreferCache, message_format: MessageFormat::Human };
+
+    let (subcommand, rest) = match args.split_first() {
+        Some((subcommand, rest)) => (subcommand.as_str(), rest),
+        None => return Ok(help),
+    };
+
+    if subcommand == "help" || subcommand == "-h" || subcommand == "--help" {
+        return Ok(help);
+    }
+
+    let arguments = parse_arguments(rest)?;
+
+    let message_format = arguments.message_format;
+    let api_mode = match (arguments.offline, arguments.update_api) {
+        (true, true) => return Err("--offline and --update-api cannot be used together".to_owned()),
+        (true, false) => ApiMode::Offline,
+        (false, true) => ApiMode::Update,
+        (false, false) => ApiMode::PreferCache,
+    };
+
+    Ok(Options { command: parse_command(subcommand, arguments)?, api_mode, message_format })
+}
+
+#[cfg(test)]
+mod can_parse {
+    use super::*;
+
+    fn parse_options(args: &[&str]) -> Result<Options, String> {
+        parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>())
+    }
+
+    fn parse_args(args: &[&str]) -> Result<Command, String> {
+        parse_options(args).map(|options| options.command)
+    }
+
+    fn parse_api_mode(args: &[&str]) -> Result<ApiMode, String> {
+        parse_options(args).map(|options| options.api_mode)
+    }
+
+    fn parse_message_format(args: &[&str]) -> Result<MessageFormat, String> {
+        parse_options(args).map(|options| options.message_format)
+    }
+
+    fn paths(paths: &[&str]) -> Vec<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    macro_rules! test_command {
+        ($test_name: ident, $args: expr, $expected: expr) => {
+            #[test]
+            fn $test_name() {
+                assert_eq!(parse_args(&$args), Ok($expected));
+            }
+        }
+    }
+
+    macro_rules! test_rejects {
+        ($test_name: ident, $args: expr) => {
+            #[test]
+            fn $test_name() {
+                assert!(parse_args(&$args).is_err());
+            }
+        }
+    }
+
+    test_command!(no_arguments, [], Command::Help);
+    test_command!(help, ["--help"], Command::Help);
+    test_command!(build_defaults_to_lua, ["build", "gui.rg"],
+        Command::Build { inputs: paths(&["gui.rg"]), output: None, format: Format::Lua });
+    test_command!(build_with_options, ["build", "gui", "styles.rgss", "-o", "out", "--format", "rbxm"],
+        Command::Build { inputs: paths(&["gui", "styles.rgss"]), output: Some(PathBuf::from("out")), format: Format::Rbxm });
+    test_command!(options_before_inputs, ["build", "-f", "json", "gui.rg"],
+        Command::Build { inputs: paths(&["gui.rg"]), output: None, format: Format::Json });
+    test_command!(watch, ["watch", "ui", "-o", "build/"],
+        Command::Watch { inputs: paths(&["ui"]), output: Some(PathBuf::from("build/")), format: Format::Lua });
+    test_command!(check, ["check", "a.rg", "b.rg"], Command::Check { inputs: paths(&["a.rg", "b.rg"]) });
+    test_command!(import, ["import", "gui.rbxmx", "-o", "gui.rg"],
+        Command::Import { input: PathBuf::from("gui.rbxmx"), output: Some(PathBuf::from("gui.rg")) });
+
+    test_command!(fmt, ["fmt", "gui"], Command::Fmt { inputs: paths(&["gui"]), check: false });
+    test_command!(fmt_check, ["fmt", "--check", "gui"], Command::Fmt { inputs: paths(&["gui"]), check: true });
+    test_command!(lsp, ["lsp", "--offline"], Command::Lsp);
+
+    test_rejects!(check_without_fmt, ["build", "gui.rg", "--check"]);
+    test_rejects!(lsp_with_inputs, ["lsp", "gui.rg"]);
+    test_rejects!(unknown_command, ["compile", "gui.rg"]);
+    test_rejects!(unknown_format, ["build", "gui.rg", "--format", "xml"]);
+    test_rejects!(unknown_option, ["build", "gui.rg", "--fast"]);
+    test_rejects!(missing_value, ["build", "gui.rg", "-o"]);
+    test_rejects!(missing_inputs, ["build", "-o", "out"]);
+    test_rejects!(check_with_output, ["check", "gui.rg", "-o", "out"]);
+    test_rejects!(import_several, ["import", "a.rbxmx", "b.rbxmx"]);
+    test_rejects!(unknown_message_format, ["check", "gui.rg", "--message-format", "xml"]);
+    test_rejects!(offline_update, ["build", "gui.rg", "--offline", "--update-api"]);
+
+    #[test]
+    fn api_modes() {
+        assert_eq!(parse_api_mode(&["build", "gui.rg"]), Ok(ApiMode::PreferCache));
+        assert_eq!(parse_api_mode(&["check", "--offline", "gui.rg"]), Ok(ApiMode::Offline));
+        assert_eq!(parse_api_mode(&["watch", "gui.rg", "--update-api"]), Ok(ApiMode::Update));
+    }
+
+    #[test]
+    fn message_formats() {
+        assert_eq!(parse_message_format(&["check", "gui.rg"]), Ok(MessageFormat::Human));
+        assert_eq!(parse_message_format(&["check", "gui.rg", "--message-format", "json"]), Ok(MessageFormat::Json));
+    }
+}
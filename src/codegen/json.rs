@@ -0,0 +1,116 @@
+use serde_json::{self, Map, Number, Value as Json};
+use rg::values::RgNode;
+use roblox::RobloxApi;
+use codegen::model::{self, ModelInstance, ModelValue};
+
+pub type JsonResult = Result<String, String>;
+
+fn number<T: Into<f64>>(n: T) -> Json {
+    Number::from_f64(n.into()).map_or(Json::Null, Json::Number)
+}
+
+// Each value carries its model type so a reader doesn't need the api to interpret it.
+fn json_value(value: &ModelValue) -> Json {
+    let (type_name, json) = match value {
+        ModelValue::String(ref s) => ("string", Json::String(s.clone())),
+        ModelValue::Content(ref s) => ("Content", Json::String(s.clone())),
+        ModelValue::Bool(b) => ("bool", Json::Bool(*b)),
+        ModelValue::Int32(n) => ("int", Json::from(*n)),
+        ModelValue::Int64(n) => ("int64", Json::from(*n)),
+        ModelValue::Float32(f) => ("float", number(*f)),
+        ModelValue::Float64(f) => ("double", number(*f)),
+        ModelValue::Color3(r, g, b) => ("Color3", Json::Array(vec![number(*r), number(*g), number(*b)])),
+        ModelValue::UDim2(xs, xo, ys, yo) =>
+            ("UDim2", Json::Array(vec![number(*xs), Json::from(*xo), number(*ys), Json::from(*yo)])),
+        ModelValue::UDim(s, o) => ("UDim", Json::Array(vec![number(*s), Json::from(*o)])),
+        ModelValue::Vector2(x, y) => ("Vector2", Json::Array(vec![number(*x), number(*y)])),
+        ModelValue::Vector3(x, y, z) => ("Vector3", Json::Array(vec![number(*x), number(*y), number(*z)])),
+        ModelValue::Rect(x0, y0, x1, y1) =>
+            ("Rect", Json::Array(vec![number(*x0), number(*y0), number(*x1), number(*y1)])),
+        ModelValue::NumberSequence(ref keypoints) => ("NumberSequence", Json::Array(keypoints.iter()
+            .map(|(time, n)| Json::Array(vec![number(*time), number(*n)]))
+            .collect())),
+        ModelValue::ColorSequence(ref keypoints) => ("ColorSequence", Json::Array(keypoints.iter()
+            .map(|(time, (r, g, b))| Json::Array(vec![number(*time), number(*r), number(*g), number(*b)]))
+            .collect())),
+        ModelValue::NumberRange(min, max) => ("NumberRange", Json::Array(vec![number(*min), number(*max)])),
+        ModelValue::Token(token) => ("token", Json::from(*token)),
+        ModelValue::Ref(ref id) => ("Ref", Json::String(id.clone())),
+    };
+
+    let mut object = Map::new();
+    object.insert("Type".to_owned(), Json::String(type_name.to_owned()));
+    object.insert("Value".to_owned(), json);
+
+    Json::Object(object)
+}
+
+fn json_instance(inst: &ModelInstance) -> Json {
+    let properties = inst.properties().iter()
+        .map(|(name, value)| (name.clone(), json_value(value)))
+        .collect();
+
+    let mut object = Map::new();
+    object.insert("ClassName".to_owned(), Json::String(inst.class_name().to_owned()));
+    if let Some(id) = inst.id() {
+        object.insert("Id".to_owned(), Json::String(id.to_owned()));
+    }
+    object.insert("Properties".to_owned(), Json::Object(properties));
+    object.insert("Children".to_owned(), Json::Array(inst.children().iter().map(json_instance).collect()));
+
+    Json::Object(object)
+}
+
+// Dumps the instance tree after styles have been applied, for tools which
+// want to consume a gui without parsing .rg themselves.
+pub fn generate(api: &RobloxApi, nodes: &[RgNode]) -> JsonResult {
+    let instances = model::from_nodes(api, nodes)?;
+    let json = Json::Array(instances.iter().map(json_instance).collect());
+
+    serde_json::to_string_pretty(&json).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod can_generate {
+    use super::*;
+    use rg::values::{RgProperty, Value};
+    use codegen::test_util::{test_api, instance, member};
+
+    fn prop(name: &str, value: Value) -> RgProperty {
+        RgProperty::new(name.to_owned(), value)
+    }
+
+    fn generate_one(props: Vec<RgProperty>) -> Json {
+        let inst = instance("TextLabel", props, vec![instance("Frame", vec![], vec![])]);
+        let text = generate(&test_api(), &[RgNode::Instance(inst)]).unwrap();
+
+        serde_json::from_str(&text).unwrap()
+    }
+
+    #[test]
+    fn instance_tree() {
+        let json = generate_one(vec![]);
+
+        assert_eq!(json[0]["ClassName"], "TextLabel");
+        assert_eq!(json[0]["Children"][0]["ClassName"], "Frame");
+        assert_eq!(json[0]["Children"][0]["Children"].as_array().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn typed_values() {
+        let json = generate_one(vec![
+            prop("Text", Value::StringLiteral("Hi".to_owned())),
+            prop("ZIndex", Value::Number(2.0)),
+            prop("TextXAlignment", member("TextXAlignment", "Right")),
+            prop("Size", Value::UDim2(0.5, 10.0, 1.0, 0.0)),
+        ]);
+
+        let props = &json[0]["Properties"];
+        assert_eq!(props["Text"]["Type"], "string");
+        assert_eq!(props["Text"]["Value"], "Hi");
+        assert_eq!(props["ZIndex"]["Value"], 2);
+        assert_eq!(props["TextXAlignment"]["Type"], "token");
+        assert_eq!(props["TextXAlignment"]["Value"], 1);
+        assert_eq!(props["Size"]["Value"][1], 10);
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use rg::values::{RgNode, RgInstance, RgProperty, Value};
+use roblox::{RobloxApi, PropertyType};
+use codegen::{property_type, enum_member};
+
+pub type LuaResult = Result<String, String>;
+
+const INSTANCES: &str = "instances";
+
+// Builds up the body of the script. Lua allows a function 200 locals, so
+// instances are kept in one table rather than a variable each.
+struct LuaWriter<'a> {
+    api: &'a RobloxApi,
+    declarations: Vec<String>,
+    parenting: Vec<String>,
+    // Where every instance with an id is kept, and the properties that
+    // refer to one, set once all instances are declared
+    ids: HashMap<String, String>,
+    references: Vec<(String, String, String)>,
+    var_count: usize,
+}
+
+fn lua_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 || c as u32 == 0x7F => quoted.push_str(&format!("\\{:03}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+impl<'a> LuaWriter<'a> {
+    fn new(api: &'a RobloxApi) -> LuaWriter<'a> {
+        LuaWriter { api, declarations: vec![], parenting: vec![], ids: HashMap::new(), references: vec![], var_count: 0 }
+    }
+
+    fn value(&self, inst: &RgInstance, prop: &RgProperty) -> LuaResult {
+        let value = prop.get_value();
+
+        if let PropertyType::Enum(ref enum_name) = property_type(self.api, inst, prop)? {
+            let (member, _) = enum_member(enum_name, prop)?;
+
+            return Ok(format!("Enum.{}.{}", enum_name, member.name()));
+        }
+
+        Ok(match value {
+            Value::StringLiteral(_) => lua_string(&value.unescaped_str()),
+            Value::Number(n) => n.to_string(),
+            Value::Boolean(b) => b.to_string(),
+            Value::Color(r, g, b) => format!("Color3.fromRGB({}, {}, {})", r, g, b),
+            Value::UDim2(xs, xo, ys, yo) => format!("UDim2.new({}, {}, {}, {})", xs, xo, ys, yo),
+            Value::UDim(s, o) => format!("UDim.new({}, {})", s, o),
+            Value::Vector2(x, y) => format!("Vector2.new({}, {})", x, y),
+            Value::Vector3(x, y, z) => format!("Vector3.new({}, {}, {})", x, y, z),
+            Value::Rect(x0, y0, x1, y1) => format!("Rect.new({}, {}, {}, {})", x0, y0, x1, y1),
+            Value::NumberSequence(ref keypoints) => {
+                let keypoints: Vec<String> = keypoints.iter()
+                    .map(|(time, n)| format!("NumberSequenceKeypoint.new({}, {})", time, n))
+                    .collect();
+
+                format!("NumberSequence.new({{{}}})", keypoints.join(", "))
+            }
+            Value::ColorSequence(ref keypoints) => {
+                let keypoints: Vec<String> = keypoints.iter()
+                    .map(|(time, (r, g, b))| format!("ColorSequenceKeypoint.new({}, Color3.fromRGB({}, {}, {}))", time, r, g, b))
+                    .collect();
+
+                format!("ColorSequence.new({{{}}})", keypoints.join(", "))
+            }
+            Value::NumberRange(min, max) => format!("NumberRange.new({}, {})", min, max),
+            Value::EnumMember(ref member) => return Err(
+                format!("Property {} cannot be set with value {}", prop.get_name(), member)),
+            // Set in generate, once every instance is declared
+            Value::Reference(ref id) => return Err(
+                format!("Property {} cannot be set with value @{}", prop.get_name(), id)),
+        })
+    }
+
+    // Declares the instance and its descendants, returning where it is kept.
+    fn instance(&mut self, inst: &RgInstance) -> LuaResult {
+        self.var_count += 1;
+        let var = format!("{}[{}]", INSTANCES, self.var_count);
+
+        let mut lines: Vec<String> = inst.docs().iter().map(|doc| format!("-- {}", doc).trim_end().to_owned()).collect();
+        lines.push(format!("{} = Instance.new({})", var, lua_string(inst.get_class_name())));
+
+        if let Some(id) = inst.id() {
+            self.ids.insert(id.to_owned(), var.clone());
+        }
+
+        for prop in inst.properties().iter().filter(|p| !p.get_name().starts_with('_')) {
+            match prop.get_value() {
+                Value::Reference(ref id) => self.references.push((var.clone(), prop.get_name().to_owned(), id.clone())),
+                _ => lines.push(format!("{}.{} = {}", var, prop.get_name(), self.value(inst, prop)?)),
+            }
+        }
+
+        self.declarations.push(lines.join("\n"));
+
+        for child in inst.children() {
+            let child_var = self.instance(child)?;
+
+            self.parenting.push(format!("{}.Parent = {}", child_var, var));
+        }
+
+        Ok(var)
+    }
+}
+
+// Emits a ModuleScript which builds the instances and returns them. Children are
+// only parented once all properties are set and their own children are parented,
+// so the tree is assembled bottom up.
+pub fn generate(api: &RobloxApi, nodes: &[RgNode]) -> LuaResult {
+    let mut writer = LuaWriter::new(api);
+    let mut roots = vec![];
+
+    for node in nodes {
+        match node {
+            RgNode::Instance(ref inst) => roots.push(writer.instance(inst)?),
+            RgNode::Property(ref prop) => return Err(
+                format!("Unexpected top level property {}", prop.get_name())),
+        }
+    }
+
+    // References can point at instances declared after them
+    let references = writer.references.iter()
+        .map(|(var, name, id)| match writer.ids.get(id) {
+            Some(target) => Ok(format!("{}.{} = {}", var, name, target)),
+            None => Err(format!("Property {} refers to @{}, which no instance has as its id", name, id)),
+        })
+        .collect::<Result<Vec<String>, String>>()?;
+
+    let returned = match roots.len() {
+        1 => roots[0].clone(),
+        _ => format!("{{ {} }}", roots.join(", ")),
+    };
+
+    let mut script = format!("-- Generated by rgss, changes will be overwritten.\n\nlocal {} = {{}}\n\n", INSTANCES);
+
+    for declaration in &writer.declarations {
+        script.push_str(declaration);
+        script.push_str("\n\n");
+    }
+
+    if !references.is_empty() {
+        script.push_str(&references.join("\n"));
+        script.push_str("\n\n");
+    }
+
+    if !writer.parenting.is_empty() {
+        script.push_str(&writer.parenting.join("\n"));
+        script.push_str("\n\n");
+    }
+
+    script.push_str(&format!("return {}\n", returned));
+
+    Ok(script)
+}
+
+#[cfg(test)]
+mod can_generate {
+    use super::*;
+    use codegen::test_util::{test_api, instance, member};
+    use rg::span::Span;
+
+    fn label(props: Vec<RgProperty>, children: Vec<RgInstance>) -> RgInstance {
+        instance("TextLabel", props, children)
+    }
+
+    macro_rules! test_lua_string {
+        ($test_name: ident, $text: expr, $expected: expr) => {
+            #[test]
+            fn $test_name() {
+                assert_eq!(lua_string($text), $expected);
+            }
+        }
+    }
+
+    test_lua_string!(plain_string, "Hi there", "\"Hi there\"");
+    test_lua_string!(string_with_quotes, "say \"hi\"", "\"say \\\"hi\\\"\"");
+    test_lua_string!(string_with_newline, "a\nb", "\"a\\nb\"");
+    test_lua_string!(string_with_control_character, "a\u{7}1", "\"a\\0071\"");
+
+    #[test]
+    fn properties_with_constructors() {
+        let inst = label(vec![
+            RgProperty::new("TextXAlignment".to_owned(), member("TextXAlignment", "Center")),
+            RgProperty::new("TextColor3".to_owned(), Value::Color(255, 20, 120)),
+            RgProperty::new_string("Name".to_owned(), "Title\\n".to_owned()),
+        ], vec![]);
+
+        let script = generate(&test_api(), &[RgNode::Instance(inst)]).unwrap();
+
+        assert!(script.contains("instances[1] = Instance.new(\"TextLabel\")\n"));
+        assert!(script.contains("instances[1].TextXAlignment = Enum.TextXAlignment.Center\n"));
+        assert!(script.contains("instances[1].TextColor3 = Color3.fromRGB(255, 20, 120)\n"));
+        assert!(script.contains("instances[1].Name = \"Title\\n\"\n"));
+        assert!(!script.contains("_ClassName"));
+        assert!(script.ends_with("return instances[1]\n"));
+    }
+
+    #[test]
+    fn references_after_declarations() {
+        let inst = instance("Frame", vec![
+            RgProperty::new("NextSelectionUp".to_owned(), Value::Reference("title".to_owned())),
+        ], vec![label(vec![], vec![]).with_id("title".to_owned(), Span::new(0, 0))]);
+
+        let script = generate(&test_api(), &[RgNode::Instance(inst)]).unwrap();
+
+        assert!(script.contains("instances[2] = Instance.new(\"TextLabel\")\n\n\
+            instances[1].NextSelectionUp = instances[2]\n\n\
+            instances[2].Parent = instances[1]\n"), "{}", script);
+    }
+
+    #[test]
+    fn sequences_with_keypoints() {
+        let inst = instance("UIGradient", vec![
+            RgProperty::new("Color".to_owned(), Value::ColorSequence(vec![(0.0, (255, 0, 0)), (1.0, (0, 0, 255))])),
+            RgProperty::new("Transparency".to_owned(), Value::NumberSequence(vec![(0.0, 0.0), (1.0, 0.5)])),
+        ], vec![]);
+
+        let script = generate(&test_api(), &[RgNode::Instance(inst)]).unwrap();
+
+        assert!(script.contains("instances[1].Color = ColorSequence.new({ColorSequenceKeypoint.new(0, Color3.fromRGB(255, 0, 0)), \
+            ColorSequenceKeypoint.new(1, Color3.fromRGB(0, 0, 255))})\n"), "{}", script);
+        assert!(script.contains("instances[1].Transparency = NumberSequence.new({NumberSequenceKeypoint.new(0, 0), \
+            NumberSequenceKeypoint.new(1, 0.5)})\n"), "{}", script);
+    }
+
+    #[test]
+    fn children_parented_after_properties() {
+        let inst = label(vec![], vec![label(vec![], vec![label(vec![], vec![])])]);
+
+        let script = generate(&test_api(), &[RgNode::Instance(inst)]).unwrap();
+
+        let grandchild = script.find("instances[3].Parent = instances[2]").unwrap();
+        let child = script.find("instances[2].Parent = instances[1]").unwrap();
+
+        assert!(script.find("instances[3] =").unwrap() < grandchild);
+        assert!(grandchild < child);
+    }
+
+    #[test]
+    fn doc_comments_as_lua_comments() {
+        let inst = label(vec![], vec![]).with_docs(vec!["The title".to_owned(), String::new()]);
+
+        let script = generate(&test_api(), &[RgNode::Instance(inst)]).unwrap();
+
+        assert!(script.contains("-- The title\n--\ninstances[1] = Instance.new(\"TextLabel\")\n"), "{}", script);
+    }
+
+    #[test]
+    fn more_instances_than_lua_has_locals() {
+        let labels = (0..300).map(|_| label(vec![], vec![])).collect();
+        let script = generate(&test_api(), &[RgNode::Instance(instance("Frame", vec![], labels))]).unwrap();
+
+        assert_eq!(script.matches("local ").count(), 1);
+        assert!(script.contains("\n\ninstances[301] = Instance.new(\"TextLabel\")\n"), "{}", script);
+        assert!(script.contains("instances[301].Parent = instances[1]\n"));
+        assert!(script.ends_with("return instances[1]\n"));
+    }
+
+    #[test]
+    fn unknown_property() {
+        let inst = label(vec![RgProperty::new("Font".to_owned(), Value::Number(1.0))], vec![]);
+
+        assert!(generate(&test_api(), &[RgNode::Instance(inst)]).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+pub mod lua;
+pub mod rbxmx;
+pub mod rbxm;
+pub mod model;
+pub mod json;
+
+use rg::values::{RgNode, RgInstance, RgProperty, EnumMember, Value};
+use roblox::{RobloxApi, PropertyType};
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Format {
+    Lua,
+    Rbxmx,
+    Rbxm,
+    Json,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Format, String> {
+        match name {
+            "lua" => Ok(Format::Lua),
+            "rbxmx" => Ok(Format::Rbxmx),
+            "rbxm" => Ok(Format::Rbxm),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("Unknown format {}, expected lua, rbxmx, rbxm or json", name)),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Lua => "lua",
+            Format::Rbxmx => "rbxmx",
+            Format::Rbxm => "rbxm",
+            Format::Json => "json",
+        }
+    }
+}
+
+// Compiles to the bytes of a file in the given format.
+pub fn generate(api: &RobloxApi, nodes: &[RgNode], format: Format) -> Result<Vec<u8>, String> {
+    match format {
+        Format::Lua => lua::generate(api, nodes).map(String::into_bytes),
+        Format::Rbxmx => rbxmx::generate(api, nodes).map(String::into_bytes),
+        Format::Rbxm => rbxm::generate(api, nodes),
+        Format::Json => json::generate(api, nodes).map(String::into_bytes),
+    }
+}
+
+// Looks up the type of a property the semantics checker has already let through.
+fn property_type<'a>(api: &'a RobloxApi, inst: &RgInstance, prop: &RgProperty) -> Result<&'a PropertyType, String> {
+    let class_name = inst.get_class_name();
+
+    let roblox_instance = api.get_instance(class_name)
+        .ok_or_else(|| format!("Class {} does not exist", class_name))?;
+
+    api.get_property(roblox_instance, prop.get_name())
+        .map(|roblox_prop| roblox_prop.get_type())
+        .ok_or_else(|| format!("Class {} does not have property {}", class_name, prop.get_name()))
+}
+
+// The member an enum property is set to and its value, which the semantics
+// checker resolves against the property's enum.
+fn enum_member<'a>(enum_name: &str, prop: &'a RgProperty) -> Result<(&'a EnumMember, u32), String> {
+    let member = match prop.get_value() {
+        Value::EnumMember(ref member) => member,
+        value => return Err(format!("Property {} cannot be set with value {:?}", prop.get_name(), value)),
+    };
+
+    match member.resolved() {
+        Some((resolved_enum, value)) if resolved_enum == enum_name => Ok((member, value)),
+        _ => Err(format!("Enum member {} of property {} has not been resolved to a {}", member, prop.get_name(), enum_name)),
+    }
+}
+
+#[cfg(test)]
+pub mod test_util {
+    use std::collections::HashSet;
+    use rg::values::{RgInstance, RgProperty, EnumMember, Value};
+    use roblox::RobloxApi;
+    use roblox::api::{Instance, Property, PropertyType, Enum};
+
+    fn add_class(api: &mut RobloxApi, name: &str, superclass: Option<&str>, props: Vec<(&str, PropertyType)>) {
+        let mut class = Instance::new(name.to_owned(), HashSet::new(), superclass.map(|s| s.to_owned()));
+
+        for (prop_name, prop_type) in props {
+            class.add_property(Property::new(prop_name.to_owned(), HashSet::new(), prop_type));
+        }
+
+        api.add_instance(class);
+    }
+
+    // A small slice of the real api, enough to cover every property type.
+    pub fn test_api() -> RobloxApi {
+        let mut api = RobloxApi::new();
+
+        add_class(&mut api, "Instance", None, vec![("Name", PropertyType::String)]);
+        add_class(&mut api, "GuiObject", Some("Instance"), vec![
+            ("Size", PropertyType::UDim2),
+            ("AnchorPoint", PropertyType::Vector2),
+            ("Visible", PropertyType::Boolean),
+            ("ZIndex", PropertyType::Int),
+            ("BackgroundColor3", PropertyType::Color),
+            ("NextSelectionUp", PropertyType::Instance("GuiObject".to_owned())),
+            ("BorderColor", PropertyType::DataType("BrickColor".to_owned())),
+        ]);
+        add_class(&mut api, "Frame", Some("GuiObject"), vec![]);
+        add_class(&mut api, "ImageLabel", Some("GuiObject"), vec![
+            ("Image", PropertyType::Content),
+            ("SliceCenter", PropertyType::Rect),
+        ]);
+        add_class(&mut api, "UICorner", Some("Instance"), vec![("CornerRadius", PropertyType::UDim)]);
+        add_class(&mut api, "UIGradient", Some("Instance"), vec![
+            ("Color", PropertyType::ColorSequence),
+            ("Transparency", PropertyType::NumberSequence),
+        ]);
+        add_class(&mut api, "ParticleEmitter", Some("Instance"), vec![
+            ("Lifetime", PropertyType::NumberRange),
+            ("Seed", PropertyType::Int64),
+        ]);
+        add_class(&mut api, "TextLabel", Some("GuiObject"), vec![
+            ("Text", PropertyType::String),
+            ("TextSize", PropertyType::Float),
+            ("TextColor3", PropertyType::Color),
+            ("TextXAlignment", PropertyType::Enum("TextXAlignment".to_owned())),
+        ]);
+
+        let mut alignment = Enum::new("TextXAlignment".to_owned(), HashSet::new());
+        alignment.add_member("Left".to_owned(), 0);
+        alignment.add_member("Right".to_owned(), 1);
+        alignment.add_member("Center".to_owned(), 2);
+        api.add_enum(alignment);
+
+        let deprecated = ["deprecated".to_owned()].iter().cloned().collect();
+        api.get_instance_mut("TextLabel").unwrap().add_property(
+            Property::new("TextColor".to_owned(), deprecated, PropertyType::DataType("BrickColor".to_owned())));
+
+        api.index_members();
+        api
+    }
+
+    // A member of one of the test api's enums, resolved as the semantics checker would.
+    pub fn member(enum_name: &str, name: &str) -> Value {
+        let value = test_api().get_enum(enum_name).and_then(|e| e.get_member_value(name)).unwrap();
+
+        Value::EnumMember(EnumMember::new(name.to_owned()).with_resolution(enum_name.to_owned(), value))
+    }
+
+    pub fn instance(class_name: &str, props: Vec<RgProperty>, children: Vec<RgInstance>) -> RgInstance {
+        let mut props = props;
+        props.push(RgProperty::new_string("_ClassName".to_owned(), class_name.to_owned()));
+
+        RgInstance::new(props, children)
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::{BTreeMap, HashSet};
+use rg::values::{RgNode, RgInstance, RgProperty, Value};
+use roblox::{RobloxApi, PropertyType};
+use codegen::{property_type, enum_member};
+
+// A property value typed the way Roblox stores it in model files.
+#[derive(PartialEq, Debug, Clone)]
+pub enum ModelValue {
+    String(String),
+    Content(String),
+    Bool(bool),
+    Int32(i32),
+    Int64(i64),
+    Float32(f32),
+    Float64(f64),
+    Color3(f32, f32, f32),
+    UDim2(f32, i32, f32, i32),
+    UDim(f32, i32),
+    Vector2(f32, f32),
+    Vector3(f32, f32, f32),
+    Rect(f32, f32, f32, f32),
+    NumberSequence(Vec<(f32, f32)>),
+    ColorSequence(Vec<(f32, (f32, f32, f32))>),
+    NumberRange(f32, f32),
+    Token(u32),
+    // The id of the instance referred to
+    Ref(String),
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct ModelInstance {
+    class_name: String,
+    properties: BTreeMap<String, ModelValue>,
+    children: Vec<ModelInstance>,
+    id: Option<String>,
+}
+
+pub type ModelResult = Result<Vec<ModelInstance>, String>;
+
+impl ModelInstance {
+    pub fn new(class_name: String, properties: BTreeMap<String, ModelValue>, children: Vec<ModelInstance>) -> ModelInstance {
+        ModelInstance { class_name, properties, children, id: None }
+    }
+
+    pub fn with_id(self, id: String) -> ModelInstance {
+        ModelInstance { id: Some(id), ..self }
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    pub fn class_name(&self) -> &str {
+        &self.class_name
+    }
+
+    pub fn properties(&self) -> &BTreeMap<String, ModelValue> {
+        &self.properties
+    }
+
+    pub fn children(&self) -> &Vec<ModelInstance> {
+        &self.children
+    }
+}
+
+fn mismatch(prop: &RgProperty) -> String {
+    format!("Property {} cannot be set with value {:?}", prop.get_name(), prop.get_value())
+}
+
+fn model_value(api: &RobloxApi, inst: &RgInstance, prop: &RgProperty) -> Result<ModelValue, String> {
+    let value = prop.get_value();
+
+    let model_value = match (property_type(api, inst, prop)?, value) {
+        (PropertyType::String, Value::StringLiteral(_)) => ModelValue::String(value.unescaped_str()),
+        (PropertyType::Content, Value::StringLiteral(_)) => ModelValue::Content(value.unescaped_str()),
+        (PropertyType::Int, Value::Number(n)) => ModelValue::Int32(*n as i32),
+        (PropertyType::Int64, Value::Number(n)) => ModelValue::Int64(*n as i64),
+        (PropertyType::Float, Value::Number(n)) => ModelValue::Float32(*n as f32),
+        (PropertyType::Double, Value::Number(n)) => ModelValue::Float64(*n),
+        (PropertyType::Boolean, Value::Boolean(b)) => ModelValue::Bool(*b),
+        (PropertyType::Color, Value::Color(r, g, b)) =>
+            ModelValue::Color3(*r as f32 / 255.0, *g as f32 / 255.0, *b as f32 / 255.0),
+        (PropertyType::UDim2, Value::UDim2(xs, xo, ys, yo)) =>
+            ModelValue::UDim2(*xs as f32, *xo as i32, *ys as f32, *yo as i32),
+        (PropertyType::UDim, Value::UDim(s, o)) => ModelValue::UDim(*s as f32, *o as i32),
+        (PropertyType::Vector2, Value::Vector2(x, y)) => ModelValue::Vector2(*x as f32, *y as f32),
+        (PropertyType::Vector3, Value::Vector3(x, y, z)) => ModelValue::Vector3(*x as f32, *y as f32, *z as f32),
+        (PropertyType::Rect, Value::Rect(x0, y0, x1, y1)) =>
+            ModelValue::Rect(*x0 as f32, *y0 as f32, *x1 as f32, *y1 as f32),
+        (PropertyType::NumberSequence, Value::NumberSequence(ref keypoints)) => ModelValue::NumberSequence(
+            keypoints.iter().map(|(time, n)| (*time as f32, *n as f32)).collect()),
+        (PropertyType::ColorSequence, Value::ColorSequence(ref keypoints)) => ModelValue::ColorSequence(
+            keypoints.iter()
+                .map(|(time, (r, g, b))| (*time as f32, (*r as f32 / 255.0, *g as f32 / 255.0, *b as f32 / 255.0)))
+                .collect()),
+        (PropertyType::NumberRange, Value::NumberRange(min, max)) => ModelValue::NumberRange(*min as f32, *max as f32),
+        (PropertyType::Instance(_), Value::Reference(ref id)) => ModelValue::Ref(id.clone()),
+        (PropertyType::Enum(ref enum_name), _) => ModelValue::Token(enum_member(enum_name, prop)?.1),
+        _ => return Err(mismatch(prop)),
+    };
+
+    Ok(model_value)
+}
+
+fn model_instance(api: &RobloxApi, inst: &RgInstance) -> Result<ModelInstance, String> {
+    let mut properties = BTreeMap::new();
+
+    for prop in inst.properties().iter().filter(|p| !p.get_name().starts_with('_')) {
+        properties.insert(prop.get_name().to_owned(), model_value(api, inst, prop)?);
+    }
+
+    let children = inst.children().iter()
+        .map(|child| model_instance(api, child))
+        .collect::<Result<Vec<ModelInstance>, String>>()?;
+
+    let model_instance = ModelInstance::new(inst.get_class_name().to_owned(), properties, children);
+
+    Ok(match inst.id() {
+        Some(id) => model_instance.with_id(id.to_owned()),
+        None => model_instance,
+    })
+}
+
+fn check_references(inst: &ModelInstance, ids: &HashSet<&str>) -> Result<(), String> {
+    for (name, value) in inst.properties() {
+        if let ModelValue::Ref(ref id) = value {
+            if !ids.contains(id.as_str()) {
+                return Err(format!("Property {} refers to @{}, which no instance has as its id", name, id));
+            }
+        }
+    }
+
+    inst.children().iter().try_for_each(|child| check_references(child, ids))
+}
+
+fn collect_ids<'a>(inst: &'a ModelInstance, ids: &mut HashSet<&'a str>) {
+    ids.extend(inst.id());
+
+    for child in inst.children() {
+        collect_ids(child, ids);
+    }
+}
+
+// Types every property using the api, ready to be written to a model file.
+// Writers can count on every reference having an instance to point to.
+pub fn from_nodes(api: &RobloxApi, nodes: &[RgNode]) -> ModelResult {
+    let instances = nodes.iter()
+        .map(|node| match node {
+            RgNode::Instance(ref inst) => model_instance(api, inst),
+            RgNode::Property(ref prop) => Err(format!("Unexpected top level property {}", prop.get_name())),
+        })
+        .collect::<ModelResult>()?;
+
+    let mut ids = HashSet::new();
+    for inst in &instances {
+        collect_ids(inst, &mut ids);
+    }
+
+    for inst in &instances {
+        check_references(inst, &ids)?;
+    }
+
+    Ok(instances)
+}
@@ -0,0 +1,284 @@
+mod writer;
+mod reader;
+
+pub use self::writer::encode;
+pub use self::reader::decode;
+
+use rg::values::RgNode;
+use roblox::RobloxApi;
+use codegen::model::{self, ModelValue};
+
+const MAGIC: &[u8] = b"<roblox!\x89\xff\x0d\x0a\x1a\x0a";
+const VERSION: u16 = 0;
+
+// Type ids of the values in a PROP chunk.
+const TYPE_STRING: u8 = 0x01;
+const TYPE_BOOL: u8 = 0x02;
+const TYPE_INT32: u8 = 0x03;
+const TYPE_FLOAT32: u8 = 0x04;
+const TYPE_FLOAT64: u8 = 0x05;
+const TYPE_UDIM: u8 = 0x06;
+const TYPE_UDIM2: u8 = 0x07;
+const TYPE_COLOR3: u8 = 0x0C;
+const TYPE_VECTOR2: u8 = 0x0D;
+const TYPE_VECTOR3: u8 = 0x0E;
+const TYPE_TOKEN: u8 = 0x12;
+const TYPE_REF: u8 = 0x13;
+const TYPE_NUMBER_SEQUENCE: u8 = 0x15;
+const TYPE_COLOR_SEQUENCE: u8 = 0x16;
+const TYPE_NUMBER_RANGE: u8 = 0x17;
+const TYPE_RECT: u8 = 0x18;
+const TYPE_INT64: u8 = 0x1B;
+
+pub type RbxmResult = Result<Vec<u8>, String>;
+
+fn type_id(value: &ModelValue) -> u8 {
+    match value {
+        ModelValue::String(_) | ModelValue::Content(_) => TYPE_STRING,
+        ModelValue::Bool(_) => TYPE_BOOL,
+        ModelValue::Int32(_) => TYPE_INT32,
+        ModelValue::Int64(_) => TYPE_INT64,
+        ModelValue::Float32(_) => TYPE_FLOAT32,
+        ModelValue::Float64(_) => TYPE_FLOAT64,
+        ModelValue::Color3(_, _, _) => TYPE_COLOR3,
+        ModelValue::UDim2(_, _, _, _) => TYPE_UDIM2,
+        ModelValue::UDim(_, _) => TYPE_UDIM,
+        ModelValue::Vector2(_, _) => TYPE_VECTOR2,
+        ModelValue::Vector3(_, _, _) => TYPE_VECTOR3,
+        ModelValue::Rect(_, _, _, _) => TYPE_RECT,
+        ModelValue::NumberSequence(_) => TYPE_NUMBER_SEQUENCE,
+        ModelValue::ColorSequence(_) => TYPE_COLOR_SEQUENCE,
+        ModelValue::NumberRange(_, _) => TYPE_NUMBER_RANGE,
+        ModelValue::Token(_) => TYPE_TOKEN,
+        ModelValue::Ref(_) => TYPE_REF,
+    }
+}
+
+// Numeric arrays are stored big endian with the bytes of all values
+// interleaved, so the first byte of every value comes first, then the
+// second and so on. Signed integers are zigzag encoded beforehand and floats
+// have their sign bit rotated to the end, which keeps small values compressible.
+
+fn interleave(bytes: &[u8], width: usize) -> Vec<u8> {
+    let count = bytes.len() / width;
+    let mut interleaved = Vec::with_capacity(bytes.len());
+
+    for byte in 0..width {
+        for value in 0..count {
+            interleaved.push(bytes[value * width + byte]);
+        }
+    }
+
+    interleaved
+}
+
+fn deinterleave(bytes: &[u8], width: usize) -> Vec<u8> {
+    let count = bytes.len() / width;
+    let mut values = vec![0; bytes.len()];
+
+    for byte in 0..width {
+        for value in 0..count {
+            values[value * width + byte] = bytes[byte * count + value];
+        }
+    }
+
+    values
+}
+
+fn zigzag32(n: i32) -> u32 {
+    ((n << 1) ^ (n >> 31)) as u32
+}
+
+fn unzigzag32(n: u32) -> i32 {
+    ((n >> 1) as i32) ^ -((n & 1) as i32)
+}
+
+fn zigzag64(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn unzigzag64(n: u64) -> i64 {
+    ((n >> 1) as i64) ^ -((n & 1) as i64)
+}
+
+fn rotate_float(f: f32) -> u32 {
+    f.to_bits().rotate_left(1)
+}
+
+fn unrotate_float(n: u32) -> f32 {
+    f32::from_bits(n.rotate_right(1))
+}
+
+// Emits a binary Roblox model, the same tree as the .rbxmx writer but smaller
+// and faster for Studio to load.
+pub fn generate(api: &RobloxApi, nodes: &[RgNode]) -> RbxmResult {
+    Ok(encode(&model::from_nodes(api, nodes)?))
+}
+
+#[cfg(test)]
+mod can_encode {
+    use super::*;
+    use std::collections::BTreeMap;
+    use codegen::model::ModelInstance;
+    use codegen::test_util::{test_api, instance, member};
+    use rg::values::{RgProperty, Value};
+
+    fn model(class_name: &str, props: Vec<(&str, ModelValue)>, children: Vec<ModelInstance>) -> ModelInstance {
+        let properties: BTreeMap<String, ModelValue> = props.into_iter()
+            .map(|(name, value)| (name.to_owned(), value))
+            .collect();
+
+        ModelInstance::new(class_name.to_owned(), properties, children)
+    }
+
+    fn round_trip(instances: Vec<ModelInstance>) {
+        let decoded = decode(&encode(&instances)).unwrap();
+
+        assert_eq!(decoded, instances);
+    }
+
+    #[test]
+    fn interleaves_bytes() {
+        let bytes = [1, 2, 3, 4, 5, 6, 7, 8];
+
+        assert_eq!(interleave(&bytes, 4), vec![1, 5, 2, 6, 3, 7, 4, 8]);
+        assert_eq!(deinterleave(&interleave(&bytes, 4), 4), bytes.to_vec());
+    }
+
+    #[test]
+    fn zigzags_integers() {
+        assert_eq!(zigzag32(0), 0);
+        assert_eq!(zigzag32(-1), 1);
+        assert_eq!(zigzag32(1), 2);
+        assert_eq!(unzigzag32(zigzag32(i32::MIN)), i32::MIN);
+        assert_eq!(unzigzag64(zigzag64(-123456789012)), -123456789012);
+    }
+
+    #[test]
+    fn rotates_floats() {
+        assert_eq!(rotate_float(-0.0), 1);
+        assert_eq!(unrotate_float(rotate_float(-2.5)), -2.5);
+    }
+
+    #[test]
+    fn starts_with_header() {
+        let bytes = encode(&[model("Frame", vec![("Name", ModelValue::String("Menu".to_owned()))], vec![])]);
+
+        assert_eq!(&bytes[..14], MAGIC);
+        assert_eq!(&bytes[14..16], &[0, 0]);
+        assert_eq!(&bytes[16..24], &[1, 0, 0, 0, 1, 0, 0, 0]);
+        assert!(bytes.ends_with(b"</roblox>"));
+    }
+
+    #[test]
+    fn round_trips_every_type() {
+        round_trip(vec![model("Frame", vec![
+            ("Name", ModelValue::String("Menu".to_owned())),
+            ("Visible", ModelValue::Bool(true)),
+            ("ZIndex", ModelValue::Int32(-3)),
+            ("Id", ModelValue::Int64(1 << 40)),
+            ("Transparency", ModelValue::Float32(0.25)),
+            ("Time", ModelValue::Float64(-1.5e10)),
+            ("BackgroundColor3", ModelValue::Color3(1.0, 0.5, 0.0)),
+            ("Size", ModelValue::UDim2(0.5, -10, 1.0, 200)),
+            ("CornerRadius", ModelValue::UDim(0.25, -8)),
+            ("AnchorPoint", ModelValue::Vector2(0.5, -1.0)),
+            ("Position", ModelValue::Vector3(1.0, 2.5, -3.0)),
+            ("SliceCenter", ModelValue::Rect(0.0, 1.0, 2.0, 3.0)),
+            ("Transparency", ModelValue::NumberSequence(vec![(0.0, 1.0), (0.25, 0.5), (1.0, 0.0)])),
+            ("Color", ModelValue::ColorSequence(vec![(0.0, (1.0, 0.0, 0.0)), (1.0, (0.0, 0.0, 1.0))])),
+            ("Lifetime", ModelValue::NumberRange(1.0, 2.5)),
+            ("TextXAlignment", ModelValue::Token(2)),
+        ], vec![])]);
+    }
+
+    #[test]
+    fn round_trips_hierarchy() {
+        let label = |name: &str, children| model("TextLabel",
+            vec![("Name", ModelValue::String(name.to_owned()))], children);
+
+        round_trip(vec![
+            model("ScreenGui", vec![("Name", ModelValue::String("Gui".to_owned()))], vec![
+                label("A", vec![label("A1", vec![]), label("A2", vec![])]),
+                model("Frame", vec![("Name", ModelValue::String("B".to_owned()))], vec![label("B1", vec![])]),
+                label("C", vec![]),
+            ]),
+            label("Other", vec![]),
+        ]);
+    }
+
+    #[test]
+    fn round_trips_instances_with_different_properties() {
+        round_trip(vec![model("Frame", vec![("Name", ModelValue::String("Root".to_owned()))], vec![
+            model("Frame", vec![
+                ("Name", ModelValue::String("Big".to_owned())),
+                ("Size", ModelValue::UDim2(1.0, 0, 1.0, 0)),
+            ], vec![]),
+            model("Frame", vec![
+                ("Name", ModelValue::String("Hidden".to_owned())),
+                ("Visible", ModelValue::Bool(false)),
+            ], vec![]),
+        ])]);
+    }
+
+    #[test]
+    fn round_trips_references() {
+        // Decoded instances are given their referent as their id
+        let name = |name: &str| ("Name", ModelValue::String(name.to_owned()));
+
+        round_trip(vec![model("Frame", vec![name("Menu"), ("NextSelectionUp", ModelValue::Ref("1".to_owned()))], vec![
+            model("TextLabel", vec![name("Title"), ("NextSelectionUp", ModelValue::Ref("0".to_owned()))], vec![])
+                .with_id("1".to_owned()),
+            model("TextLabel", vec![name("Other")], vec![]),
+        ]).with_id("0".to_owned())]);
+    }
+
+    #[test]
+    fn names_unnamed_instances_after_their_class() {
+        let decoded = decode(&encode(&[model("Frame", vec![], vec![])])).unwrap();
+
+        assert_eq!(decoded, vec![model("Frame", vec![("Name", ModelValue::String("Frame".to_owned()))], vec![])]);
+    }
+
+    #[test]
+    fn content_decodes_as_string() {
+        let decoded = decode(&encode(&[model("ImageLabel", vec![
+            ("Name", ModelValue::String("Icon".to_owned())),
+            ("Image", ModelValue::Content("rbxassetid://1".to_owned())),
+        ], vec![])])).unwrap();
+
+        assert_eq!(decoded[0].properties()["Image"], ModelValue::String("rbxassetid://1".to_owned()));
+    }
+
+    #[test]
+    fn round_trips_compiled_instances() {
+        let inst = instance("Frame", vec![
+            RgProperty::new_string("Name".to_owned(), "Menu".to_owned()),
+            RgProperty::new("BackgroundColor3".to_owned(), Value::Color(255, 20, 120)),
+        ], vec![instance("TextLabel", vec![
+            RgProperty::new_string("Name".to_owned(), "Title".to_owned()),
+            RgProperty::new("TextXAlignment".to_owned(), member("TextXAlignment", "Center")),
+            RgProperty::new("TextSize".to_owned(), Value::Number(24.0)),
+        ], vec![])]);
+
+        let nodes = [RgNode::Instance(inst)];
+        let decoded = decode(&generate(&test_api(), &nodes).unwrap()).unwrap();
+
+        assert_eq!(decoded, model::from_nodes(&test_api(), &nodes).unwrap());
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(decode(b"<roblox xmlns").is_err());
+        assert!(decode(&encode(&[model("Frame", vec![], vec![])])[..40]).is_err());
+    }
+
+    #[test]
+    fn rejects_oversized_chunks() {
+        let mut bytes = encode(&[model("Frame", vec![], vec![])]);
+        bytes[40..44].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let error = decode(&bytes).unwrap_err();
+        assert!(error.contains("claims 4294967295 bytes"), "{}", error);
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use codegen::model::{ModelInstance, ModelResult, ModelValue};
+use lz4_flex;
+use super::*;
+
+// LZ4 can't expand a block by more than this, so a longer length is a lie.
+const MAX_LZ4_RATIO: usize = 255;
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(bytes: &'a [u8]) -> Cursor<'a> {
+        Cursor { bytes, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() - self.pos < n {
+            return Err(format!("Unexpected end of data at byte {}", self.pos));
+        }
+
+        self.pos += n;
+        Ok(&self.bytes[self.pos - n..self.pos])
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+
+    // A list of keypoints for each value, every keypoint being `width` floats.
+    fn f32_lists(&mut self, count: usize, width: usize) -> Result<Vec<Vec<f32>>, String> {
+        (0..count)
+            .map(|_| {
+                let len = self.u32()? as usize * width;
+
+                (0..len).map(|_| self.f32()).collect()
+            })
+            .collect()
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.u32()? as usize;
+
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| "String is not valid UTF-8".to_owned())
+    }
+
+    fn u32_array(&mut self, count: usize) -> Result<Vec<u32>, String> {
+        let bytes = deinterleave(self.take(count * 4)?, 4);
+
+        Ok(bytes.chunks(4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+            .collect())
+    }
+
+    fn i32_array(&mut self, count: usize) -> Result<Vec<i32>, String> {
+        Ok(self.u32_array(count)?.into_iter().map(unzigzag32).collect())
+    }
+
+    fn i64_array(&mut self, count: usize) -> Result<Vec<i64>, String> {
+        let bytes = deinterleave(self.take(count * 8)?, 8);
+
+        Ok(bytes.chunks(8)
+            .map(|b| unzigzag64(u64::from_be_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]])))
+            .collect())
+    }
+
+    fn f32_array(&mut self, count: usize) -> Result<Vec<f32>, String> {
+        Ok(self.u32_array(count)?.into_iter().map(unrotate_float).collect())
+    }
+
+    fn referents(&mut self, count: usize) -> Result<Vec<i32>, String> {
+        let mut previous = 0;
+
+        Ok(self.i32_array(count)?.into_iter()
+            .map(|delta| {
+                previous += delta;
+                previous
+            })
+            .collect())
+    }
+}
+
+fn read_values(cursor: &mut Cursor, type_id: u8, count: usize) -> Result<Vec<ModelValue>, String> {
+    let values = match type_id {
+        TYPE_STRING => (0..count)
+            .map(|_| cursor.string().map(ModelValue::String))
+            .collect::<Result<Vec<ModelValue>, String>>()?,
+        TYPE_BOOL => cursor.take(count)?.iter().map(|b| ModelValue::Bool(*b != 0)).collect(),
+        TYPE_INT32 => cursor.i32_array(count)?.into_iter().map(ModelValue::Int32).collect(),
+        TYPE_INT64 => cursor.i64_array(count)?.into_iter().map(ModelValue::Int64).collect(),
+        TYPE_FLOAT32 => cursor.f32_array(count)?.into_iter().map(ModelValue::Float32).collect(),
+        TYPE_FLOAT64 => cursor.take(count * 8)?.chunks(8)
+            .map(|b| ModelValue::Float64(f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]])))
+            .collect(),
+        TYPE_COLOR3 => {
+            let (r, g, b) = (cursor.f32_array(count)?, cursor.f32_array(count)?, cursor.f32_array(count)?);
+
+            (0..count).map(|i| ModelValue::Color3(r[i], g[i], b[i])).collect()
+        }
+        TYPE_UDIM2 => {
+            let (xs, ys) = (cursor.f32_array(count)?, cursor.f32_array(count)?);
+            let (xo, yo) = (cursor.i32_array(count)?, cursor.i32_array(count)?);
+
+            (0..count).map(|i| ModelValue::UDim2(xs[i], xo[i], ys[i], yo[i])).collect()
+        }
+        TYPE_UDIM => {
+            let (s, o) = (cursor.f32_array(count)?, cursor.i32_array(count)?);
+
+            (0..count).map(|i| ModelValue::UDim(s[i], o[i])).collect()
+        }
+        TYPE_VECTOR2 => {
+            let (x, y) = (cursor.f32_array(count)?, cursor.f32_array(count)?);
+
+            (0..count).map(|i| ModelValue::Vector2(x[i], y[i])).collect()
+        }
+        TYPE_VECTOR3 => {
+            let (x, y, z) = (cursor.f32_array(count)?, cursor.f32_array(count)?, cursor.f32_array(count)?);
+
+            (0..count).map(|i| ModelValue::Vector3(x[i], y[i], z[i])).collect()
+        }
+        TYPE_RECT => {
+            let (x0, y0) = (cursor.f32_array(count)?, cursor.f32_array(count)?);
+            let (x1, y1) = (cursor.f32_array(count)?, cursor.f32_array(count)?);
+
+            (0..count).map(|i| ModelValue::Rect(x0[i], y0[i], x1[i], y1[i])).collect()
+        }
+        // Envelopes are dropped, they can't be written in .rg
+        TYPE_NUMBER_SEQUENCE => cursor.f32_lists(count, 3)?.into_iter()
+            .map(|floats| ModelValue::NumberSequence(floats.chunks(3).map(|k| (k[0], k[1])).collect()))
+            .collect(),
+        TYPE_COLOR_SEQUENCE => cursor.f32_lists(count, 5)?.into_iter()
+            .map(|floats| ModelValue::ColorSequence(floats.chunks(5).map(|k| (k[0], (k[1], k[2], k[3]))).collect()))
+            .collect(),
+        TYPE_NUMBER_RANGE => (0..count)
+            .map(|_| Ok(ModelValue::NumberRange(cursor.f32()?, cursor.f32()?)))
+            .collect::<Result<Vec<ModelValue>, String>>()?,
+        TYPE_TOKEN => cursor.u32_array(count)?.into_iter().map(ModelValue::Token).collect(),
+        _ => return Err(format!("Unsupported property type 0x{:02X}", type_id)),
+    };
+
+    Ok(values)
+}
+
+struct DecodedInstance {
+    class_name: String,
+    properties: BTreeMap<String, ModelValue>,
+    children: Vec<i32>,
+}
+
+fn build(referent: i32, instances: &mut HashMap<i32, DecodedInstance>, referenced: &HashSet<i32>) -> Result<ModelInstance, String> {
+    let decoded = instances.remove(&referent)
+        .ok_or_else(|| format!("Instance {} is missing or parented twice", referent))?;
+
+    let children = decoded.children.iter()
+        .map(|child| build(*child, instances, referenced))
+        .collect::<Result<Vec<ModelInstance>, String>>()?;
+
+    let inst = ModelInstance::new(decoded.class_name, decoded.properties, children);
+
+    Ok(if referenced.contains(&referent) { inst.with_id(referent.to_string()) } else { inst })
+}
+
+// Reads back a binary model, used to check what the writer produces.
+pub fn decode(bytes: &[u8]) -> ModelResult {
+    let mut cursor = Cursor::new(bytes);
+
+    if cursor.take(MAGIC.len()).ok() != Some(MAGIC) {
+        return Err("Not a binary Roblox model".to_owned());
+    }
+
+    let version = cursor.take(2)?;
+    if version != VERSION.to_le_bytes() {
+        return Err(format!("Unsupported model version {:?}", version));
+    }
+
+    cursor.take(16)?;
+
+    let mut classes: HashMap<u32, (String, Vec<i32>)> = HashMap::new();
+    let mut instances: HashMap<i32, DecodedInstance> = HashMap::new();
+    let mut referenced: HashSet<i32> = HashSet::new();
+    let mut roots = vec![];
+    let mut ended = false;
+
+    while !ended && !cursor.is_empty() {
+        let name = cursor.take(4)?;
+        let compressed_len = cursor.u32()? as usize;
+        let len = cursor.u32()? as usize;
+        cursor.take(4)?;
+
+        let data = if compressed_len == 0 {
+            cursor.take(len)?.to_vec()
+        } else {
+            let compressed = cursor.take(compressed_len)?;
+
+            // The length comes from the file, so it is checked before being allocated
+            if len > compressed_len * MAX_LZ4_RATIO {
+                return Err(format!("{} chunk claims {} bytes, more than its {} compressed bytes can hold",
+                    String::from_utf8_lossy(name), len, compressed_len));
+            }
+
+            lz4_flex::block::decompress(compressed, len)
+                .map_err(|e| format!("Failed to decompress {} chunk: {}", String::from_utf8_lossy(name), e))?
+        };
+
+        let mut chunk = Cursor::new(&data);
+
+        match name {
+            b"INST" => {
+                let class_id = chunk.u32()?;
+                let class_name = chunk.string()?;
+                chunk.u8()?;
+                let count = chunk.u32()? as usize;
+                let referents = chunk.referents(count)?;
+
+                for referent in &referents {
+                    instances.insert(*referent, DecodedInstance {
+                        class_name: class_name.clone(), properties: BTreeMap::new(), children: vec![],
+                    });
+                }
+
+                classes.insert(class_id, (class_name, referents));
+            }
+            b"PROP" => {
+                let class_id = chunk.u32()?;
+                let prop_name = chunk.string()?;
+                let type_id = chunk.u8()?;
+
+                let referents = &classes.get(&class_id)
+                    .ok_or_else(|| format!("Property {} belongs to unknown class {}", prop_name, class_id))?.1;
+
+                // References point at the referent of another instance, which
+                // becomes that instance's id. Nil references are left out.
+                let values: Vec<Option<ModelValue>> = match type_id {
+                    TYPE_REF => chunk.referents(referents.len())?.into_iter()
+                        .map(|target| if target < 0 {
+                            None
+                        } else {
+                            referenced.insert(target);
+                            Some(ModelValue::Ref(target.to_string()))
+                        })
+                        .collect(),
+                    _ => read_values(&mut chunk, type_id, referents.len())?.into_iter().map(Some).collect(),
+                };
+
+                for (referent, value) in referents.iter().zip(values) {
+                    if let (Some(inst), Some(value)) = (instances.get_mut(referent), value) {
+                        inst.properties.insert(prop_name.clone(), value);
+                    }
+                }
+            }
+            b"PRNT" => {
+                chunk.u8()?;
+                let count = chunk.u32()? as usize;
+                let children = chunk.referents(count)?;
+                let parents = chunk.referents(count)?;
+
+                for (child, parent) in children.into_iter().zip(parents) {
+                    if parent < 0 {
+                        roots.push(child);
+                    } else {
+                        instances.get_mut(&parent)
+                            .ok_or_else(|| format!("Instance {} has unknown parent {}", child, parent))?
+                            .children.push(child);
+                    }
+                }
+            }
+            b"END\0" => ended = true,
+            _ => {}
+        }
+    }
+
+    if !ended {
+        return Err("Model is missing its END chunk".to_owned());
+    }
+
+    roots.into_iter()
+        .map(|root| build(root, &mut instances, &referenced))
+        .collect()
+}
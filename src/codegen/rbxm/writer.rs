@@ -0,0 +1,273 @@
+use std::collections::{BTreeMap, HashMap};
+use codegen::model::{ModelInstance, ModelValue};
+use lz4_flex;
+use super::*;
+
+fn write_u32(buf: &mut Vec<u8>, n: u32) {
+    buf.extend_from_slice(&n.to_le_bytes());
+}
+
+fn write_f32(buf: &mut Vec<u8>, f: f32) {
+    buf.extend_from_slice(&f.to_le_bytes());
+}
+
+fn write_string(buf: &mut Vec<u8>, s: &str) {
+    write_u32(buf, s.len() as u32);
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn write_u32_array(buf: &mut Vec<u8>, values: &[u32]) {
+    let bytes: Vec<u8> = values.iter().flat_map(|n| n.to_be_bytes().to_vec()).collect();
+
+    buf.extend(interleave(&bytes, 4));
+}
+
+fn write_i32_array(buf: &mut Vec<u8>, values: &[i32]) {
+    let zigzagged: Vec<u32> = values.iter().map(|n| zigzag32(*n)).collect();
+
+    write_u32_array(buf, &zigzagged);
+}
+
+fn write_i64_array(buf: &mut Vec<u8>, values: &[i64]) {
+    let bytes: Vec<u8> = values.iter().flat_map(|n| zigzag64(*n).to_be_bytes().to_vec()).collect();
+
+    buf.extend(interleave(&bytes, 8));
+}
+
+fn write_f32_array(buf: &mut Vec<u8>, values: &[f32]) {
+    let rotated: Vec<u32> = values.iter().map(|f| rotate_float(*f)).collect();
+
+    write_u32_array(buf, &rotated);
+}
+
+// Referents are stored as the difference to the previous one.
+fn write_referents(buf: &mut Vec<u8>, referents: &[i32]) {
+    let mut previous = 0;
+    let deltas: Vec<i32> = referents.iter()
+        .map(|referent| {
+            let delta = referent - previous;
+            previous = *referent;
+            delta
+        })
+        .collect();
+
+    write_i32_array(buf, &deltas);
+}
+
+// Writes all values of one property, which must all have the same type.
+fn write_values(buf: &mut Vec<u8>, values: &[&ModelValue], ids: &HashMap<&str, i32>) {
+    macro_rules! collect {
+        ($($pattern: pat)|+ => $field: expr) => {
+            values.iter().map(|value| match value {
+                $($pattern)|+ => $field,
+                _ => unreachable!("property values of different types"),
+            }).collect::<Vec<_>>()
+        }
+    }
+
+    match values[0] {
+        ModelValue::String(_) | ModelValue::Content(_) => {
+            for s in collect!(ModelValue::String(s) | ModelValue::Content(s) => s) {
+                write_string(buf, s);
+            }
+        }
+        ModelValue::Bool(_) => buf.extend(collect!(ModelValue::Bool(b) => *b as u8)),
+        ModelValue::Int32(_) => write_i32_array(buf, &collect!(ModelValue::Int32(n) => *n)),
+        ModelValue::Int64(_) => write_i64_array(buf, &collect!(ModelValue::Int64(n) => *n)),
+        ModelValue::Float32(_) => write_f32_array(buf, &collect!(ModelValue::Float32(f) => *f)),
+        ModelValue::Float64(_) => {
+            for f in collect!(ModelValue::Float64(f) => *f) {
+                buf.extend_from_slice(&f.to_le_bytes());
+            }
+        }
+        ModelValue::Color3(_, _, _) => {
+            write_f32_array(buf, &collect!(ModelValue::Color3(r, _, _) => *r));
+            write_f32_array(buf, &collect!(ModelValue::Color3(_, g, _) => *g));
+            write_f32_array(buf, &collect!(ModelValue::Color3(_, _, b) => *b));
+        }
+        ModelValue::UDim2(_, _, _, _) => {
+            write_f32_array(buf, &collect!(ModelValue::UDim2(xs, _, _, _) => *xs));
+            write_f32_array(buf, &collect!(ModelValue::UDim2(_, _, ys, _) => *ys));
+            write_i32_array(buf, &collect!(ModelValue::UDim2(_, xo, _, _) => *xo));
+            write_i32_array(buf, &collect!(ModelValue::UDim2(_, _, _, yo) => *yo));
+        }
+        ModelValue::UDim(_, _) => {
+            write_f32_array(buf, &collect!(ModelValue::UDim(s, _) => *s));
+            write_i32_array(buf, &collect!(ModelValue::UDim(_, o) => *o));
+        }
+        ModelValue::Vector2(_, _) => {
+            write_f32_array(buf, &collect!(ModelValue::Vector2(x, _) => *x));
+            write_f32_array(buf, &collect!(ModelValue::Vector2(_, y) => *y));
+        }
+        ModelValue::Vector3(_, _, _) => {
+            write_f32_array(buf, &collect!(ModelValue::Vector3(x, _, _) => *x));
+            write_f32_array(buf, &collect!(ModelValue::Vector3(_, y, _) => *y));
+            write_f32_array(buf, &collect!(ModelValue::Vector3(_, _, z) => *z));
+        }
+        ModelValue::Rect(_, _, _, _) => {
+            write_f32_array(buf, &collect!(ModelValue::Rect(x0, _, _, _) => *x0));
+            write_f32_array(buf, &collect!(ModelValue::Rect(_, y0, _, _) => *y0));
+            write_f32_array(buf, &collect!(ModelValue::Rect(_, _, x1, _) => *x1));
+            write_f32_array(buf, &collect!(ModelValue::Rect(_, _, _, y1) => *y1));
+        }
+        // Sequences and ranges are plain little endian floats, value after value.
+        // Every keypoint ends with its envelope, which is always 0 here.
+        ModelValue::NumberSequence(_) => {
+            for keypoints in collect!(ModelValue::NumberSequence(keypoints) => keypoints) {
+                write_u32(buf, keypoints.len() as u32);
+
+                for (time, n) in keypoints {
+                    for f in &[*time, *n, 0.0] {
+                        write_f32(buf, *f);
+                    }
+                }
+            }
+        }
+        ModelValue::ColorSequence(_) => {
+            for keypoints in collect!(ModelValue::ColorSequence(keypoints) => keypoints) {
+                write_u32(buf, keypoints.len() as u32);
+
+                for (time, (r, g, b)) in keypoints {
+                    for f in &[*time, *r, *g, *b, 0.0] {
+                        write_f32(buf, *f);
+                    }
+                }
+            }
+        }
+        ModelValue::NumberRange(_, _) => {
+            for (min, max) in collect!(ModelValue::NumberRange(min, max) => (*min, *max)) {
+                write_f32(buf, min);
+                write_f32(buf, max);
+            }
+        }
+        ModelValue::Token(_) => write_u32_array(buf, &collect!(ModelValue::Token(token) => *token)),
+        ModelValue::Ref(_) => write_referents(buf, &collect!(ModelValue::Ref(id) => ids[id.as_str()])),
+    }
+}
+
+fn write_chunk(out: &mut Vec<u8>, name: &[u8; 4], data: &[u8], compress: bool) {
+    out.extend_from_slice(name);
+
+    if compress {
+        let compressed = lz4_flex::block::compress(data);
+
+        write_u32(out, compressed.len() as u32);
+        write_u32(out, data.len() as u32);
+        write_u32(out, 0);
+        out.extend(compressed);
+    } else {
+        write_u32(out, 0);
+        write_u32(out, data.len() as u32);
+        write_u32(out, 0);
+        out.extend_from_slice(data);
+    }
+}
+
+// Instances in a PROP chunk must all have that property, so instances of a
+// class are split into one INST chunk for each set of properties they have.
+struct ClassGroup<'a> {
+    class_name: &'a str,
+    properties: Vec<(&'a str, u8)>,
+    instances: Vec<(i32, BTreeMap<&'a str, ModelValue>)>,
+}
+
+struct Flattened<'a> {
+    groups: Vec<ClassGroup<'a>>,
+    parents: Vec<(i32, i32)>,
+    // The referent of every instance with an id
+    ids: HashMap<&'a str, i32>,
+    count: i32,
+}
+
+impl<'a> Flattened<'a> {
+    fn add(&mut self, inst: &'a ModelInstance, parent: i32) {
+        let referent = self.count;
+        self.count += 1;
+
+        if let Some(id) = inst.id() {
+            self.ids.insert(id, referent);
+        }
+
+        let mut properties: BTreeMap<&'a str, ModelValue> = inst.properties().iter()
+            .map(|(name, value)| (name.as_str(), value.clone()))
+            .collect();
+
+        // Studio names instances after their class when they are created
+        properties.entry("Name")
+            .or_insert_with(|| ModelValue::String(inst.class_name().to_owned()));
+
+        let signature: Vec<(&str, u8)> = properties.iter()
+            .map(|(name, value)| (*name, type_id(value)))
+            .collect();
+
+        let existing = self.groups.iter()
+            .position(|group| group.class_name == inst.class_name() && group.properties == signature);
+
+        let index = existing.unwrap_or_else(|| {
+            self.groups.push(ClassGroup { class_name: inst.class_name(), properties: signature, instances: vec![] });
+            self.groups.len() - 1
+        });
+
+        self.groups[index].instances.push((referent, properties));
+        self.parents.push((referent, parent));
+
+        for child in inst.children() {
+            self.add(child, referent);
+        }
+    }
+}
+
+pub fn encode(instances: &[ModelInstance]) -> Vec<u8> {
+    let mut flattened = Flattened { groups: vec![], parents: vec![], ids: HashMap::new(), count: 0 };
+    for inst in instances {
+        flattened.add(inst, -1);
+    }
+
+    let mut out = MAGIC.to_vec();
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    write_u32(&mut out, flattened.groups.len() as u32);
+    write_u32(&mut out, flattened.count as u32);
+    out.extend_from_slice(&[0; 8]);
+
+    for (class_id, group) in flattened.groups.iter().enumerate() {
+        let referents: Vec<i32> = group.instances.iter().map(|(referent, _)| *referent).collect();
+
+        let mut data = vec![];
+        write_u32(&mut data, class_id as u32);
+        write_string(&mut data, group.class_name);
+        data.push(0);
+        write_u32(&mut data, referents.len() as u32);
+        write_referents(&mut data, &referents);
+
+        write_chunk(&mut out, b"INST", &data, true);
+    }
+
+    for (class_id, group) in flattened.groups.iter().enumerate() {
+        for (name, type_id) in &group.properties {
+            let values: Vec<&ModelValue> = group.instances.iter()
+                .map(|(_, properties)| &properties[name])
+                .collect();
+
+            let mut data = vec![];
+            write_u32(&mut data, class_id as u32);
+            write_string(&mut data, name);
+            data.push(*type_id);
+            write_values(&mut data, &values, &flattened.ids);
+
+            write_chunk(&mut out, b"PROP", &data, true);
+        }
+    }
+
+    let children: Vec<i32> = flattened.parents.iter().map(|(child, _)| *child).collect();
+    let parents: Vec<i32> = flattened.parents.iter().map(|(_, parent)| *parent).collect();
+
+    let mut data = vec![0];
+    write_u32(&mut data, children.len() as u32);
+    write_referents(&mut data, &children);
+    write_referents(&mut data, &parents);
+    write_chunk(&mut out, b"PRNT", &data, true);
+
+    write_chunk(&mut out, b"END\0", b"</roblox>", false);
+
+    out
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use rg::values::RgNode;
+use roblox::RobloxApi;
+use codegen::model::{self, ModelInstance, ModelValue};
+
+pub type RbxmxResult = Result<String, String>;
+
+const HEADER: &str = "<roblox xmlns:xmime=\"http://www.w3.org/2005/05/xmlmime\" \
+    xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+    xsi:noNamespaceSchemaLocation=\"http://www.roblox.com/roblox.xsd\" version=\"4\">";
+
+struct RbxmxWriter<'a> {
+    lines: Vec<String>,
+    referent_count: usize,
+    // The referents of instances with an id, known before any item is written
+    // so references can point further down the file
+    referents: HashMap<&'a str, String>,
+}
+
+fn referent(count: usize) -> String {
+    format!("RBX{:032X}", count)
+}
+
+// Numbers the items in the order they will be written.
+fn collect_referents<'a>(inst: &'a ModelInstance, count: &mut usize, referents: &mut HashMap<&'a str, String>) {
+    *count += 1;
+
+    if let Some(id) = inst.id() {
+        referents.insert(id, referent(*count));
+    }
+
+    for child in inst.children() {
+        collect_referents(child, count, referents);
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::new();
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+// The typed element for one property, its tag coming from the property's type.
+fn property(name: &str, value: &ModelValue, referents: &HashMap<&str, String>) -> String {
+    let name = escape_xml(name);
+
+    match value {
+        ModelValue::String(ref s) => format!("<string name=\"{}\">{}</string>", name, escape_xml(s)),
+        ModelValue::Content(ref url) => format!("<Content name=\"{}\"><url>{}</url></Content>", name, escape_xml(url)),
+        ModelValue::Bool(b) => format!("<bool name=\"{}\">{}</bool>", name, b),
+        ModelValue::Int32(n) => format!("<int name=\"{}\">{}</int>", name, n),
+        ModelValue::Int64(n) => format!("<int64 name=\"{}\">{}</int64>", name, n),
+        ModelValue::Float32(n) => format!("<float name=\"{}\">{}</float>", name, n),
+        ModelValue::Float64(n) => format!("<double name=\"{}\">{}</double>", name, n),
+        ModelValue::Color3(r, g, b) => format!(
+            "<Color3 name=\"{}\"><R>{}</R><G>{}</G><B>{}</B></Color3>", name, r, g, b),
+        ModelValue::UDim2(xs, xo, ys, yo) => format!(
+            "<UDim2 name=\"{}\"><XS>{}</XS><XO>{}</XO><YS>{}</YS><YO>{}</YO></UDim2>", name, xs, xo, ys, yo),
+        ModelValue::UDim(s, o) => format!("<UDim name=\"{}\"><S>{}</S><O>{}</O></UDim>", name, s, o),
+        ModelValue::Vector2(x, y) => format!("<Vector2 name=\"{}\"><X>{}</X><Y>{}</Y></Vector2>", name, x, y),
+        ModelValue::Vector3(x, y, z) => format!(
+            "<Vector3 name=\"{}\"><X>{}</X><Y>{}</Y><Z>{}</Z></Vector3>", name, x, y, z),
+        ModelValue::Rect(x0, y0, x1, y1) => format!(
+            "<Rect2D name=\"{}\"><min><X>{}</X><Y>{}</Y></min><max><X>{}</X><Y>{}</Y></max></Rect2D>",
+            name, x0, y0, x1, y1),
+        // Sequences are space separated keypoints, each ending with its envelope
+        ModelValue::NumberSequence(ref keypoints) => {
+            let text: String = keypoints.iter().map(|(time, n)| format!("{} {} 0 ", time, n)).collect();
+
+            format!("<NumberSequence name=\"{}\">{}</NumberSequence>", name, text)
+        }
+        ModelValue::ColorSequence(ref keypoints) => {
+            let text: String = keypoints.iter()
+                .map(|(time, (r, g, b))| format!("{} {} {} {} 0 ", time, r, g, b))
+                .collect();
+
+            format!("<ColorSequence name=\"{}\">{}</ColorSequence>", name, text)
+        }
+        ModelValue::NumberRange(min, max) => format!("<NumberRange name=\"{}\">{} {} </NumberRange>", name, min, max),
+        ModelValue::Token(token) => format!("<token name=\"{}\">{}</token>", name, token),
+        ModelValue::Ref(ref id) => format!("<Ref name=\"{}\">{}</Ref>", name, referents[id.as_str()]),
+    }
+}
+
+impl<'a> RbxmxWriter<'a> {
+    fn new(instances: &'a [ModelInstance]) -> RbxmxWriter<'a> {
+        let mut referents = HashMap::new();
+        let mut count = 0;
+
+        for inst in instances {
+            collect_referents(inst, &mut count, &mut referents);
+        }
+
+        RbxmxWriter { lines: vec![], referent_count: 0, referents }
+    }
+
+    fn push(&mut self, depth: usize, line: String) {
+        self.lines.push(format!("{}{}", "\t".repeat(depth), line));
+    }
+
+    fn next_referent(&mut self) -> String {
+        self.referent_count += 1;
+
+        referent(self.referent_count)
+    }
+
+    fn item(&mut self, inst: &ModelInstance, depth: usize) {
+        let referent = self.next_referent();
+        self.push(depth, format!("<Item class=\"{}\" referent=\"{}\">", escape_xml(inst.class_name()), referent));
+
+        self.push(depth + 1, "<Properties>".to_owned());
+        for (name, value) in inst.properties() {
+            let element = property(name, value, &self.referents);
+            self.push(depth + 2, element);
+        }
+        self.push(depth + 1, "</Properties>".to_owned());
+
+        for child in inst.children() {
+            self.item(child, depth + 1);
+        }
+
+        self.push(depth, "</Item>".to_owned());
+    }
+}
+
+// Emits a Roblox XML model with every top level instance as its own item.
+pub fn generate(api: &RobloxApi, nodes: &[RgNode]) -> RbxmxResult {
+    let instances = model::from_nodes(api, nodes)?;
+    let mut writer = RbxmxWriter::new(&instances);
+
+    writer.push(0, HEADER.to_owned());
+    writer.push(1, "<External>null</External>".to_owned());
+    writer.push(1, "<External>nil</External>".to_owned());
+
+    for inst in &instances {
+        writer.item(inst, 1);
+    }
+
+    writer.push(0, "</roblox>".to_owned());
+
+    Ok(writer.lines.join("\n") + "\n")
+}
+
+#[cfg(test)]
+mod can_generate {
+    use super::*;
+    use rg::values::{RgInstance, RgProperty, EnumMember, Value};
+    use codegen::test_util::{test_api, instance, member};
+    use rg::span::Span;
+
+    fn prop(name: &str, value: Value) -> RgProperty {
+        RgProperty::new(name.to_owned(), value)
+    }
+
+    fn generate_one(inst: RgInstance) -> String {
+        generate(&test_api(), &[RgNode::Instance(inst)]).unwrap()
+    }
+
+    macro_rules! test_property_element {
+        ($test_name: ident, $class: expr, $prop: expr, $expected: expr) => {
+            #[test]
+            fn $test_name() {
+                let xml = generate_one(instance($class, vec![$prop], vec![]));
+
+                assert!(xml.contains(concat!("\t\t\t", $expected, "\n")), "{} not in {}", $expected, xml);
+            }
+        }
+    }
+
+    test_property_element!(string_element, "TextLabel", prop("Text", Value::StringLiteral("a < \\\"b\\\"".to_owned())),
+        "<string name=\"Text\">a &lt; &quot;b&quot;</string>");
+    test_property_element!(content_element, "ImageLabel", prop("Image", Value::StringLiteral("rbxassetid://1".to_owned())),
+        "<Content name=\"Image\"><url>rbxassetid://1</url></Content>");
+    test_property_element!(int_element, "Frame", prop("ZIndex", Value::Number(3.0)),
+        "<int name=\"ZIndex\">3</int>");
+    test_property_element!(float_element, "TextLabel", prop("TextSize", Value::Number(14.5)),
+        "<float name=\"TextSize\">14.5</float>");
+    test_property_element!(bool_element, "Frame", prop("Visible", Value::Boolean(false)),
+        "<bool name=\"Visible\">false</bool>");
+    test_property_element!(color_element, "Frame", prop("BackgroundColor3", Value::Color(255, 0, 0)),
+        "<Color3 name=\"BackgroundColor3\"><R>1</R><G>0</G><B>0</B></Color3>");
+    test_property_element!(udim_element, "UICorner", prop("CornerRadius", Value::UDim(0.25, 8.0)),
+        "<UDim name=\"CornerRadius\"><S>0.25</S><O>8</O></UDim>");
+    test_property_element!(vector2_element, "Frame", prop("AnchorPoint", Value::Vector2(0.5, 1.0)),
+        "<Vector2 name=\"AnchorPoint\"><X>0.5</X><Y>1</Y></Vector2>");
+    test_property_element!(rect_element, "ImageLabel", prop("SliceCenter", Value::Rect(1.0, 2.0, 3.0, 4.0)),
+        "<Rect2D name=\"SliceCenter\"><min><X>1</X><Y>2</Y></min><max><X>3</X><Y>4</Y></max></Rect2D>");
+    test_property_element!(number_sequence_element, "UIGradient",
+        prop("Transparency", Value::NumberSequence(vec![(0.0, 1.0), (1.0, 0.5)])),
+        "<NumberSequence name=\"Transparency\">0 1 0 1 0.5 0 </NumberSequence>");
+    test_property_element!(color_sequence_element, "UIGradient",
+        prop("Color", Value::ColorSequence(vec![(0.0, (255, 0, 0)), (1.0, (0, 0, 255))])),
+        "<ColorSequence name=\"Color\">0 1 0 0 0 1 0 0 1 0 </ColorSequence>");
+    test_property_element!(number_range_element, "ParticleEmitter", prop("Lifetime", Value::NumberRange(1.0, 2.5)),
+        "<NumberRange name=\"Lifetime\">1 2.5 </NumberRange>");
+    test_property_element!(udim2_element, "Frame", prop("Size", Value::UDim2(0.5, 10.0, 1.0, -20.0)),
+        "<UDim2 name=\"Size\"><XS>0.5</XS><XO>10</XO><YS>1</YS><YO>-20</YO></UDim2>");
+    test_property_element!(token_element, "TextLabel", prop("TextXAlignment", member("TextXAlignment", "Center")),
+        "<token name=\"TextXAlignment\">2</token>");
+
+    #[test]
+    fn nested_items_with_unique_referents() {
+        let xml = generate_one(instance("Frame", vec![], vec![
+            instance("TextLabel", vec![], vec![]),
+            instance("TextLabel", vec![], vec![]),
+        ]));
+
+        assert!(xml.starts_with("<roblox "));
+        assert!(xml.contains("\t<Item class=\"Frame\" referent=\"RBX00000000000000000000000000000001\">\n"));
+        assert!(xml.contains("\t\t<Item class=\"TextLabel\" referent=\"RBX00000000000000000000000000000002\">\n"));
+        assert!(xml.contains("\t\t<Item class=\"TextLabel\" referent=\"RBX00000000000000000000000000000003\">\n"));
+        assert!(xml.ends_with("\t</Item>\n</roblox>\n"));
+    }
+
+    #[test]
+    fn references_by_referent() {
+        let inst = instance("Frame", vec![prop("NextSelectionUp", Value::Reference("title".to_owned()))], vec![
+            instance("TextLabel", vec![], vec![]),
+            instance("TextLabel", vec![], vec![]).with_id("title".to_owned(), Span::new(0, 0)),
+        ]);
+        let xml = generate_one(inst);
+
+        assert!(xml.contains("\t\t\t<Ref name=\"NextSelectionUp\">RBX00000000000000000000000000000003</Ref>\n"), "{}", xml);
+    }
+
+    #[test]
+    fn reject_unknown_references() {
+        let inst = instance("Frame", vec![prop("NextSelectionUp", Value::Reference("title".to_owned()))], vec![]);
+
+        assert!(generate(&test_api(), &[RgNode::Instance(inst)]).is_err());
+    }
+
+    #[test]
+    fn unresolved_enum_member() {
+        let inst = instance("TextLabel", vec![
+            prop("TextXAlignment", Value::EnumMember(EnumMember::new("Center".to_owned()))),
+        ], vec![]);
+
+        assert!(generate(&test_api(), &[RgNode::Instance(inst)]).is_err());
+    }
+}
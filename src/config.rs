@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde_json;
+
+pub const CONFIG_FILE: &str = "rgss.json";
+
+// Project settings, read from the nearest rgss.json.
+#[derive(PartialEq, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    // An api dump to always build against, relative to the config file
+    api: Option<PathBuf>,
+    #[serde(default)]
+    fmt: FmtConfig,
+}
+
+// How rgss fmt lays out sources.
+#[derive(PartialEq, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FmtConfig {
+    // Alphabetically rather than in the order they were written
+    #[serde(default)]
+    sort_properties: bool,
+}
+
+impl Config {
+    pub fn parse(text: &str, dir: &Path) -> Result<Config, String> {
+        let mut config: Config = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        config.api = config.api.map(|api| dir.join(api));
+
+        Ok(config)
+    }
+
+    // Searches the directory and its parents, a missing config is the default one.
+    pub fn find(start: &Path) -> Result<Config, String> {
+        for dir in start.ancestors() {
+            let path = dir.join(CONFIG_FILE);
+
+            if path.is_file() {
+                let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+                return Config::parse(&text, dir).map_err(|e| format!("{}: {}", path.display(), e));
+            }
+        }
+
+        Ok(Config::default())
+    }
+
+    pub fn api(&self) -> Option<&Path> {
+        self.api.as_deref()
+    }
+
+    pub fn sort_properties(&self) -> bool {
+        self.fmt.sort_properties
+    }
+}
+
+#[cfg(test)]
+mod can_parse {
+    use super::*;
+
+    #[test]
+    fn empty_config() {
+        assert_eq!(Config::parse("{}", Path::new("ui")), Ok(Config::default()));
+    }
+
+    #[test]
+    fn pinned_api() {
+        let config = Config::parse(r#"{ "api": "roblox/api.json" }"#, Path::new("ui")).unwrap();
+
+        assert_eq!(config.api(), Some(Path::new("ui/roblox/api.json")));
+    }
+
+    #[test]
+    fn fmt_settings() {
+        assert!(Config::parse(r#"{ "fmt": { "sort_properties": true } }"#, Path::new("ui")).unwrap().sort_properties());
+        assert!(Config::parse(r#"{ "fmt": { "sort": true } }"#, Path::new("ui")).is_err());
+    }
+
+    #[test]
+    fn unknown_setting() {
+        assert!(Config::parse(r#"{ "apis": "api.json" }"#, Path::new("ui")).is_err());
+    }
+}
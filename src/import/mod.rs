@@ -0,0 +1,381 @@
+pub mod rbxmx;
+
+use std::collections::{HashMap, HashSet};
+use rg::{parser, printer};
+use rg::diagnostics::{codes, Diagnostic, Diagnostics};
+use rg::span::Span;
+use rg::values::{escape_str, EnumMember, RgInstance, RgNode, RgProperty, Value};
+use roblox::{RobloxApi, PropertyType};
+use roblox::api::Property;
+use roblox::defaults::get_default;
+use codegen::model::{ModelInstance, ModelValue};
+use codegen::rbxm;
+
+// Only properties a script could set are worth writing into a .rg file.
+fn is_emittable(prop: &Property) -> bool {
+    let hidden = ["readonly", "hidden", "deprecated", "notscriptable"].iter()
+        .any(|tag| prop.has_tag(tag));
+
+    !hidden && !prop.has_tag_containing("Security")
+}
+
+// Floats are printed as their shortest representation to avoid 0.2 becoming 0.200000003.
+fn shortest(f: f32) -> Option<f64> {
+    if f.is_finite() {
+        f.to_string().parse().ok()
+    } else {
+        None
+    }
+}
+
+fn float_value(f: f32) -> Option<Value> {
+    shortest(f).map(Value::Number)
+}
+
+fn color_channel(c: f32) -> i64 {
+    (c.clamp(0.0, 1.0) * 255.0).round() as i64
+}
+
+// A readable id for a referenced instance, made from its name.
+fn readable_id(inst: &ModelInstance, taken: &mut HashSet<String>) -> String {
+    let name = match inst.properties().get("Name") {
+        Some(ModelValue::String(ref name)) => name.as_str(),
+        _ => inst.class_name(),
+    };
+
+    let mut chars = name.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '_');
+    let mut base: String = match chars.next() {
+        Some(c) if c.is_ascii_digit() => format!("_{}", c),
+        Some(c) => c.to_ascii_lowercase().to_string(),
+        None => "instance".to_owned(),
+    };
+    base.extend(chars);
+
+    let id = (1..).map(|n| if n == 1 { base.clone() } else { format!("{}{}", base, n) })
+        .find(|id| !taken.contains(id))
+        .unwrap();
+
+    taken.insert(id.clone());
+    id
+}
+
+// Maps the ids the model gave referenced instances to the ids they get in .rg.
+fn collect_ids<'a>(inst: &'a ModelInstance, taken: &mut HashSet<String>, ids: &mut HashMap<&'a str, String>) {
+    if let Some(id) = inst.id() {
+        ids.insert(id, readable_id(inst, taken));
+    }
+
+    for child in inst.children() {
+        collect_ids(child, taken, ids);
+    }
+}
+
+fn rg_value(api: &RobloxApi, prop: &Property, value: &ModelValue, ids: &HashMap<&str, String>) -> Option<Value> {
+    let rg_value = match (prop.get_type(), value) {
+        (PropertyType::String, ModelValue::String(ref s))
+        | (PropertyType::Content, ModelValue::Content(ref s))
+        | (PropertyType::Content, ModelValue::String(ref s)) => Value::StringLiteral(escape_str(s)),
+        (PropertyType::Boolean, ModelValue::Bool(b)) => Value::Boolean(*b),
+        (_, ModelValue::Int32(n)) => Value::Number(f64::from(*n)),
+        (_, ModelValue::Int64(n)) => Value::Number(*n as f64),
+        (_, ModelValue::Float32(f)) => float_value(*f)?,
+        (_, ModelValue::Float64(f)) if f.is_finite() => Value::Number(*f),
+        (PropertyType::Color, ModelValue::Color3(r, g, b)) =>
+            Value::Color(color_channel(*r), color_channel(*g), color_channel(*b)),
+        (PropertyType::UDim2, ModelValue::UDim2(xs, xo, ys, yo)) =>
+            Value::UDim2(f64::from(*xs), f64::from(*xo), f64::from(*ys), f64::from(*yo)),
+        (PropertyType::UDim, ModelValue::UDim(s, o)) => Value::UDim(f64::from(*s), f64::from(*o)),
+        (PropertyType::Vector2, ModelValue::Vector2(x, y)) => Value::Vector2(f64::from(*x), f64::from(*y)),
+        (PropertyType::Vector3, ModelValue::Vector3(x, y, z)) =>
+            Value::Vector3(f64::from(*x), f64::from(*y), f64::from(*z)),
+        (PropertyType::Rect, ModelValue::Rect(x0, y0, x1, y1)) =>
+            Value::Rect(f64::from(*x0), f64::from(*y0), f64::from(*x1), f64::from(*y1)),
+        (PropertyType::NumberSequence, ModelValue::NumberSequence(ref keypoints)) => Value::NumberSequence(
+            keypoints.iter()
+                .map(|(time, n)| Some((shortest(*time)?, shortest(*n)?)))
+                .collect::<Option<Vec<(f64, f64)>>>()?),
+        (PropertyType::ColorSequence, ModelValue::ColorSequence(ref keypoints)) => Value::ColorSequence(
+            keypoints.iter()
+                .map(|(time, (r, g, b))| Some((shortest(*time)?, (color_channel(*r), color_channel(*g), color_channel(*b)))))
+                .collect::<Option<Vec<(f64, (i64, i64, i64))>>>()?),
+        (PropertyType::NumberRange, ModelValue::NumberRange(min, max)) => Value::NumberRange(shortest(*min)?, shortest(*max)?),
+        (PropertyType::Instance(_), ModelValue::Ref(ref id)) => Value::Reference(ids.get(id.as_str())?.clone()),
+        (PropertyType::Enum(ref enum_name), ModelValue::Token(token)) => {
+            let member = api.get_enum(enum_name)?.get_member_name(*token)?;
+
+            Value::EnumMember(EnumMember::new(member.to_owned()))
+        }
+        _ => return None,
+    };
+
+    Some(rg_value)
+}
+
+// The properties which had to be left out, by the position of their instance
+// in the order instances are written.
+#[derive(Default)]
+struct Dropped {
+    instances: usize,
+    properties: Vec<(usize, String)>,
+}
+
+fn import_instance(api: &RobloxApi, inst: &ModelInstance, ids: &HashMap<&str, String>, dropped: &mut Dropped)
+    -> Result<RgInstance, String> {
+    let class_name = inst.class_name();
+    let roblox_instance = api.get_instance(class_name)
+        .ok_or_else(|| format!("Class {} does not exist", class_name))?;
+
+    let index = dropped.instances;
+    dropped.instances += 1;
+
+    let mut props = vec![];
+
+    for (name, value) in inst.properties() {
+        let prop = match api.get_property(roblox_instance, name) {
+            Some(prop) if is_emittable(prop) => prop,
+            _ => continue,
+        };
+
+        let value = match (rg_value(api, prop, value, ids), value) {
+            (Some(value), _) => value,
+            (None, ModelValue::Ref(_)) => {
+                dropped.properties.push((index,
+                    format!("Dropped property {}, the instance it refers to is not in the model", name)));
+                continue;
+            }
+            (None, _) => continue,
+        };
+
+        // Instances are named after their class unless told otherwise
+        let default = match name.as_str() {
+            "Name" => Some(Value::StringLiteral(escape_str(class_name))),
+            _ => get_default(api, class_name, name),
+        };
+
+        if default.as_ref() != Some(&value) {
+            props.push(RgProperty::new(name.clone(), value));
+        }
+    }
+
+    props.push(RgProperty::new_string("_ClassName".to_owned(), class_name.to_owned()));
+
+    let children = inst.children().iter()
+        .map(|child| import_instance(api, child, ids, dropped))
+        .collect::<Result<Vec<RgInstance>, String>>()?;
+
+    let rg_instance = RgInstance::new(props, children);
+
+    Ok(match inst.id().and_then(|id| ids.get(id)) {
+        Some(id) => rg_instance.with_id(id.clone(), Span::default()),
+        None => rg_instance,
+    })
+}
+
+fn class_spans(inst: &RgInstance, spans: &mut Vec<Span>) {
+    spans.push(inst.class_span());
+
+    for child in inst.children() {
+        class_spans(child, spans);
+    }
+}
+
+// Prints the instances as .rg source, warning about each dropped property at
+// the class name of its instance in that source.
+fn decompile(api: &RobloxApi, model: &[ModelInstance]) -> Result<(String, Diagnostics), String> {
+    let mut taken = HashSet::new();
+    let mut ids = HashMap::new();
+    let mut dropped = Dropped::default();
+
+    for inst in model {
+        collect_ids(inst, &mut taken, &mut ids);
+    }
+
+    let instances = model.iter()
+        .map(|inst| import_instance(api, inst, &ids, &mut dropped))
+        .collect::<Result<Vec<RgInstance>, String>>()?;
+
+    let source = printer::print_file(&instances, false);
+    let mut diagnostics = Diagnostics::default();
+
+    if !dropped.properties.is_empty() {
+        let (nodes, _) = parser::parse_file(&source).map_err(|d| d.message().to_owned())?;
+        let mut spans = vec![];
+
+        for node in &nodes {
+            if let RgNode::Instance(ref inst) = node {
+                class_spans(inst, &mut spans);
+            }
+        }
+
+        for (index, message) in dropped.properties {
+            diagnostics.push(Diagnostic::warning(codes::DROPPED_PROPERTY, message, spans[index]));
+        }
+    }
+
+    Ok((source, diagnostics))
+}
+
+// Turns a Roblox XML model into .rg source.
+pub fn decompile_rbxmx(api: &RobloxApi, text: &str) -> Result<(String, Diagnostics), String> {
+    decompile(api, &rbxmx::decode(text)?)
+}
+
+// Turns a binary Roblox model into .rg source.
+pub fn decompile_rbxm(api: &RobloxApi, bytes: &[u8]) -> Result<(String, Diagnostics), String> {
+    decompile(api, &rbxm::decode(bytes)?)
+}
+
+#[cfg(test)]
+mod can_decompile {
+    use super::*;
+    use codegen::test_util::test_api;
+
+    fn model(items: &str) -> String {
+        format!("<roblox version=\"4\">{}</roblox>", items)
+    }
+
+    fn decompile(items: &str) -> String {
+        let (source, diagnostics) = decompile_rbxmx(&test_api(), &model(items)).unwrap();
+
+        assert_eq!(diagnostics, Diagnostics::default());
+        source
+    }
+
+    #[test]
+    fn typed_properties() {
+        let source = decompile(r#"<Item class="TextLabel" referent="RBX1"><Properties>
+            <string name="Name">Title</string>
+            <string name="Text">Say "hi"</string>
+            <float name="TextSize">14.5</float>
+            <Color3uint8 name="TextColor3">4294901760</Color3uint8>
+            <UDim2 name="Size"><XS>0.5</XS><XO>10</XO><YS>1</YS><YO>-20</YO></UDim2>
+            <token name="TextXAlignment">0</token>
+        </Properties></Item>"#);
+
+        assert_eq!(source, "TextLabel \"Title\" {\n    \
+            Size: UDim2(0.5, 10, 1, -20)\n    \
+            Text: \"Say \\\"hi\\\"\"\n    \
+            TextColor3: #FF0000\n    \
+            TextSize: 14.5\n    \
+            TextXAlignment: Left\n}\n");
+    }
+
+    #[test]
+    fn vector_properties() {
+        let source = decompile(r#"<Item class="ImageLabel" referent="RBX1"><Properties>
+            <Vector2 name="AnchorPoint"><X>0.5</X><Y>0.5</Y></Vector2>
+            <Rect2D name="SliceCenter"><min><X>8</X><Y>8</Y></min><max><X>24</X><Y>24</Y></max></Rect2D>
+        </Properties></Item>"#);
+
+        assert_eq!(source, "ImageLabel {\n    AnchorPoint: Vector2(0.5, 0.5)\n    SliceCenter: Rect(8, 8, 24, 24)\n}\n");
+    }
+
+    #[test]
+    fn sequence_properties() {
+        let source = decompile(r#"<Item class="UIGradient" referent="RBX1"><Properties>
+            <ColorSequence name="Color">0 1 0 0 0 1 0 0 1 0 </ColorSequence>
+            <NumberSequence name="Transparency">0 0 0 0.3 0.2 0 1 1 0 </NumberSequence>
+        </Properties></Item>"#);
+
+        assert_eq!(source, "UIGradient {\n    \
+            Color: ColorSequence(0: #FF0000, 1: #0000FF)\n    \
+            Transparency: NumberSequence(0: 0, 0.3: 0.2, 1: 1)\n}\n");
+    }
+
+    #[test]
+    fn references_as_ids() {
+        let source = decompile(r#"<Item class="Frame" referent="RBX1"><Properties>
+            <Ref name="NextSelectionUp">RBX3</Ref>
+        </Properties>
+            <Item class="TextLabel" referent="RBX2"><Properties>
+                <string name="Name">1 Play!</string>
+                <Ref name="NextSelectionUp">null</Ref>
+            </Properties></Item>
+            <Item class="TextLabel" referent="RBX3"><Properties>
+                <string name="Name">Play</string>
+                <Ref name="NextSelectionUp">RBX2</Ref>
+            </Properties></Item>
+        </Item>"#);
+
+        assert_eq!(source, "Frame {\n    \
+            NextSelectionUp: @play\n\n    \
+            TextLabel \"1 Play!\" @_1Play {}\n\n    \
+            TextLabel \"Play\" @play {\n        NextSelectionUp: @_1Play\n    }\n}\n");
+    }
+
+    #[test]
+    fn references_from_binary_models() {
+        use rg::parser::parse_str;
+        use rg::styles::StyleSheet;
+
+        let source = "Frame @menu {\n    TextLabel {\n        NextSelectionUp: @menu\n    }\n}\n";
+        let (nodes, _) = parse_str(source, &StyleSheet::default(), &test_api());
+        let bytes = rbxm::generate(&test_api(), &nodes).unwrap();
+
+        assert_eq!(decompile_rbxm(&test_api(), &bytes).unwrap().0, "Frame @frame {\n    \
+            TextLabel {\n        NextSelectionUp: @frame\n    }\n}\n");
+    }
+
+    #[test]
+    fn warn_about_references_out_of_the_model() {
+        let (source, diagnostics) = decompile_rbxmx(&test_api(), &model(r#"<Item class="Frame" referent="RBX1">
+            <Item class="TextLabel" referent="RBX2"><Properties>
+                <Ref name="NextSelectionUp">RBX9</Ref>
+            </Properties></Item>
+        </Item>"#)).unwrap();
+
+        assert_eq!(source, "Frame {\n    TextLabel {}\n}\n");
+
+        let warnings: Vec<(&str, Span)> = diagnostics.iter().map(|d| (d.code(), d.span())).collect();
+        assert_eq!(warnings, vec![(codes::DROPPED_PROPERTY, Span::new(12, 21))]);
+        assert_eq!(diagnostics.iter().next().unwrap().message(),
+            "Dropped property NextSelectionUp, the instance it refers to is not in the model");
+    }
+
+    #[test]
+    fn omit_defaults() {
+        let source = decompile(r#"<Item class="Frame" referent="RBX1"><Properties>
+            <string name="Name">Frame</string>
+            <bool name="Visible">true</bool>
+            <int name="ZIndex">1</int>
+            <Color3 name="BackgroundColor3"><R>0</R><G>0</G><B>0</B></Color3>
+        </Properties></Item>"#);
+
+        assert_eq!(source, "Frame {\n    BackgroundColor3: #000000\n}\n");
+    }
+
+    #[test]
+    fn nested_items() {
+        let source = decompile(r#"<Item class="Frame" referent="RBX1"><Properties />
+            <Item class="ImageLabel" referent="RBX2"><Properties>
+                <Content name="Image"><url>rbxassetid://1</url></Content>
+            </Properties></Item>
+            <Item class="Frame" referent="RBX3"><Properties /></Item>
+        </Item>"#);
+
+        assert_eq!(source, "Frame {\n    \
+            ImageLabel {\n        Image: \"rbxassetid://1\"\n    }\n\n    \
+            Frame {}\n}\n");
+    }
+
+    #[test]
+    fn skip_unknown_types() {
+        let source = decompile(r#"<Item class="Frame" referent="RBX1"><Properties>
+            <CoordinateFrame name="CFrame"><X>0</X></CoordinateFrame>
+        </Properties></Item>"#);
+
+        assert_eq!(source, "Frame {}\n");
+    }
+
+    #[test]
+    fn reject_unknown_class() {
+        let result = decompile_rbxmx(&test_api(), &model(r#"<Item class="Part" referent="RBX1" />"#));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn reject_other_documents() {
+        assert!(decompile_rbxmx(&test_api(), "<html></html>").is_err());
+    }
+}
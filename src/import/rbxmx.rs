@@ -0,0 +1,193 @@
+use std::collections::{BTreeMap, HashSet};
+use xml::reader::{EventReader, XmlEvent};
+use codegen::model::{ModelInstance, ModelResult, ModelValue};
+
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn new(name: String, attributes: Vec<(String, String)>) -> Element {
+        Element { name, attributes, children: vec![], text: String::new() }
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter()
+            .find(|(attr, _)| attr == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|child| child.text.trim())
+    }
+}
+
+fn parse_document(text: &str) -> Result<Element, String> {
+    let mut stack = vec![Element::new(String::new(), vec![])];
+
+    for event in EventReader::from_str(text) {
+        match event.map_err(|e| e.to_string())? {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                let attributes = attributes.into_iter()
+                    .map(|attr| (attr.name.local_name, attr.value))
+                    .collect();
+
+                stack.push(Element::new(name.local_name, attributes));
+            }
+            XmlEvent::EndElement { .. } => {
+                let element = stack.pop().unwrap();
+                stack.last_mut().unwrap().children.push(element);
+            }
+            XmlEvent::Characters(text) | XmlEvent::CData(text) | XmlEvent::Whitespace(text) => {
+                stack.last_mut().unwrap().text.push_str(&text);
+            }
+            _ => {}
+        }
+    }
+
+    stack.pop()
+        .and_then(|document| document.children.into_iter().next())
+        .ok_or_else(|| "Model has no root element".to_owned())
+}
+
+// Older files store colours packed into a single integer.
+fn packed_color(text: &str) -> Option<ModelValue> {
+    let packed = text.trim().parse::<u32>().ok()?;
+    let channel = |shift: u32| ((packed >> shift) & 0xFF) as f32 / 255.0;
+
+    Some(ModelValue::Color3(channel(16), channel(8), channel(0)))
+}
+
+// Space separated keypoints of `width` numbers, the last being the envelope.
+fn keypoints(text: &str, width: usize) -> Option<Vec<Vec<f32>>> {
+    let numbers = text.split_whitespace()
+        .map(|n| n.parse().ok())
+        .collect::<Option<Vec<f32>>>()?;
+
+    if numbers.len() % width != 0 {
+        return None;
+    }
+
+    Some(numbers.chunks(width).map(|k| k[..width - 1].to_vec()).collect())
+}
+
+// None for value types which have no model value.
+fn parse_value(element: &Element) -> Option<ModelValue> {
+    let text = element.text.trim();
+
+    let value = match element.name.as_str() {
+        "string" => ModelValue::String(element.text.clone()),
+        "Content" => ModelValue::Content(element.child_text("url").unwrap_or("").to_owned()),
+        "bool" => ModelValue::Bool(text == "true"),
+        "int" => ModelValue::Int32(text.parse().ok()?),
+        "int64" => ModelValue::Int64(text.parse().ok()?),
+        "float" => ModelValue::Float32(text.parse().ok()?),
+        "double" => ModelValue::Float64(text.parse().ok()?),
+        "token" => ModelValue::Token(text.parse().ok()?),
+        "Color3" if element.child("R").is_some() => ModelValue::Color3(
+            element.child_text("R")?.parse().ok()?,
+            element.child_text("G")?.parse().ok()?,
+            element.child_text("B")?.parse().ok()?),
+        "Color3" | "Color3uint8" => packed_color(text)?,
+        "UDim2" => ModelValue::UDim2(
+            element.child_text("XS")?.parse().ok()?,
+            element.child_text("XO")?.parse().ok()?,
+            element.child_text("YS")?.parse().ok()?,
+            element.child_text("YO")?.parse().ok()?),
+        "UDim" => ModelValue::UDim(
+            element.child_text("S")?.parse().ok()?,
+            element.child_text("O")?.parse().ok()?),
+        "Vector2" => ModelValue::Vector2(
+            element.child_text("X")?.parse().ok()?,
+            element.child_text("Y")?.parse().ok()?),
+        "Vector3" => ModelValue::Vector3(
+            element.child_text("X")?.parse().ok()?,
+            element.child_text("Y")?.parse().ok()?,
+            element.child_text("Z")?.parse().ok()?),
+        "Rect2D" => {
+            let (min, max) = (element.child("min")?, element.child("max")?);
+
+            ModelValue::Rect(
+                min.child_text("X")?.parse().ok()?,
+                min.child_text("Y")?.parse().ok()?,
+                max.child_text("X")?.parse().ok()?,
+                max.child_text("Y")?.parse().ok()?)
+        }
+        "NumberSequence" => ModelValue::NumberSequence(
+            keypoints(text, 3)?.into_iter().map(|k| (k[0], k[1])).collect()),
+        "ColorSequence" => ModelValue::ColorSequence(
+            keypoints(text, 5)?.into_iter().map(|k| (k[0], (k[1], k[2], k[3]))).collect()),
+        // The referent of the item referred to, or null for none
+        "Ref" if text != "null" && text != "nil" => ModelValue::Ref(text.to_owned()),
+        "NumberRange" => {
+            let mut range = text.split_whitespace().map(|n| n.parse().ok());
+
+            ModelValue::NumberRange(range.next()??, range.next()??)
+        }
+        _ => return None,
+    };
+
+    Some(value)
+}
+
+// The referents some property refers to, which become those items' ids.
+fn collect_referenced<'a>(element: &'a Element, referenced: &mut HashSet<&'a str>) {
+    if element.name == "Ref" {
+        referenced.insert(element.text.trim());
+    }
+
+    for child in &element.children {
+        collect_referenced(child, referenced);
+    }
+}
+
+fn parse_item(item: &Element, referenced: &HashSet<&str>) -> Result<ModelInstance, String> {
+    let class_name = item.attribute("class")
+        .ok_or_else(|| "Item is missing its class".to_owned())?;
+
+    let mut properties = BTreeMap::new();
+
+    if let Some(props) = item.child("Properties") {
+        for prop in &props.children {
+            if let (Some(name), Some(value)) = (prop.attribute("name"), parse_value(prop)) {
+                properties.insert(name.to_owned(), value);
+            }
+        }
+    }
+
+    let children = item.children.iter()
+        .filter(|child| child.name == "Item")
+        .map(|child| parse_item(child, referenced))
+        .collect::<Result<Vec<ModelInstance>, String>>()?;
+
+    let inst = ModelInstance::new(class_name.to_owned(), properties, children);
+
+    Ok(match item.attribute("referent") {
+        Some(referent) if referenced.contains(referent) => inst.with_id(referent.to_owned()),
+        _ => inst,
+    })
+}
+
+// Reads the items of a Roblox XML model, skipping properties of types .rg can't express.
+pub fn decode(text: &str) -> ModelResult {
+    let root = parse_document(text)?;
+
+    if root.name != "roblox" {
+        return Err(format!("Expected a roblox model but found <{}>", root.name));
+    }
+
+    let mut referenced = HashSet::new();
+    collect_referenced(&root, &mut referenced);
+
+    root.children.iter()
+        .filter(|child| child.name == "Item")
+        .map(|child| parse_item(child, &referenced))
+        .collect()
+}
@@ -0,0 +1,387 @@
+use roblox::RobloxApi;
+use roblox::api::PropertyType;
+use rg::values::{RgInstance, RgNode};
+use rg::styles::StyleSheet;
+use rg::span::Span;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum CompletionKind {
+    Class,
+    Property,
+    Value,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+    pub detail: String,
+}
+
+// Where the cursor is, as far as the text before it tells. Files being typed
+// rarely parse, so this is worked out without the parser.
+#[derive(PartialEq, Debug)]
+enum Context {
+    // Outside of every instance and style rule
+    TopLevel,
+    // Where a property or child could go, inside an instance of the class
+    Body(Option<String>),
+    // After the colon of one of the class's properties
+    Value(Option<String>, String),
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric()
+}
+
+fn context_at(text: &str, offset: usize) -> Context {
+    let before = &text[..offset];
+    let mut classes: Vec<Option<String>> = vec![];
+    let mut last_word: Option<&str> = None;
+    let mut statement_start = 0;
+    let mut in_string = false;
+    let mut chars = before.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if in_string {
+            match c {
+                '\\' => { chars.next(); }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match c {
+            '"' => in_string = true,
+            '/' if before[i..].starts_with("//") || before[i..].starts_with("/*") => {
+                let end = if before[i..].starts_with("//") {
+                    before[i..].find('\n').map_or(before.len(), |end| i + end)
+                } else {
+                    before[i + 2..].find("*/").map_or(before.len(), |end| i + 2 + end + 2)
+                };
+
+                while let Some(&(j, _)) = chars.peek() {
+                    if j >= end {
+                        break;
+                    }
+                    chars.next();
+                }
+            }
+            // An id, which comes between the class and the brace
+            '@' => while let Some(&(_, next)) = chars.peek() {
+                if !is_word_char(next) && next != '_' {
+                    break;
+                }
+                chars.next();
+            },
+            '{' => classes.push(last_word.take().map(|word| word.to_owned())),
+            '}' => { classes.pop(); }
+            _ if is_word_char(c) => {
+                let mut end = i + c.len_utf8();
+                while let Some(&(j, next)) = chars.peek() {
+                    if !is_word_char(next) {
+                        break;
+                    }
+                    end = j + next.len_utf8();
+                    chars.next();
+                }
+
+                if c.is_ascii_alphabetic() {
+                    last_word = Some(&before[i..end]);
+                }
+            }
+            _ => {}
+        }
+
+        if "{};\n".contains(c) {
+            statement_start = i + 1;
+        }
+    }
+
+    let class = match classes.last() {
+        Some(class) => class.clone(),
+        None => return Context::TopLevel,
+    };
+
+    // Only the first word after the colon is the value, anything after
+    // whitespace is the start of the next statement.
+    let statement = &before[statement_start..];
+    if let Some(colon) = statement.find(':') {
+        let name = statement[..colon].trim();
+        let value = statement[colon + 1..].trim_start();
+
+        if !name.is_empty() && name.chars().all(is_word_char) && !value.contains(char::is_whitespace) {
+            return Context::Value(class, name.to_owned());
+        }
+    }
+
+    Context::Body(class)
+}
+
+pub fn type_name(prop_type: &PropertyType) -> String {
+    match prop_type {
+        PropertyType::Enum(ref name) | PropertyType::Instance(ref name) | PropertyType::DataType(ref name) => name.clone(),
+        prop_type => format!("{:?}", prop_type),
+    }
+}
+
+fn complete_classes(api: &RobloxApi) -> Vec<Completion> {
+    api.get_instances().iter()
+        .filter(|inst| !inst.has_tag("notCreatable"))
+        .map(|inst| Completion {
+            label: inst.get_name().to_owned(),
+            kind: CompletionKind::Class,
+            detail: inst.get_superclass().clone().unwrap_or_default(),
+        })
+        .collect()
+}
+
+fn complete_properties(api: &RobloxApi, class: &Option<String>) -> Vec<Completion> {
+    let inst = match class.as_ref().and_then(|class| api.get_instance(class)) {
+        Some(inst) => inst,
+        None => return vec![],
+    };
+
+    api.get_properties(inst).into_iter()
+        .filter(|prop| !prop.has_tag("hidden") && !prop.has_tag("readonly"))
+        .map(|prop| Completion {
+            label: prop.get_name().to_owned(),
+            kind: CompletionKind::Property,
+            detail: type_name(prop.get_type()),
+        })
+        .collect()
+}
+
+fn complete_values(api: &RobloxApi, class: &Option<String>, prop_name: &str) -> Vec<Completion> {
+    let prop = class.as_ref()
+        .and_then(|class| api.get_instance(class))
+        .and_then(|inst| api.get_property(inst, prop_name));
+
+    let value = |label: &str, detail: &str| Completion {
+        label: label.to_owned(),
+        kind: CompletionKind::Value,
+        detail: detail.to_owned(),
+    };
+
+    match prop.map(|prop| prop.get_type()) {
+        Some(PropertyType::Boolean) => vec![value("true", "Boolean"), value("false", "Boolean")],
+        Some(PropertyType::Enum(ref enum_name)) => {
+            let mut members = api.get_enum(enum_name).map(|e| e.get_member_names()).unwrap_or_default();
+            members.sort();
+
+            members.into_iter().map(|member| value(member, enum_name)).collect()
+        }
+        _ => vec![],
+    }
+}
+
+// Stylesheets can't nest instances, so only .rg files are offered classes
+// inside of a body.
+pub fn complete(api: &RobloxApi, text: &str, offset: usize, is_stylesheet: bool) -> Vec<Completion> {
+    match context_at(text, offset) {
+        Context::TopLevel => complete_classes(api),
+        Context::Body(ref class) if is_stylesheet => complete_properties(api, class),
+        Context::Body(ref class) => {
+            let mut completions = complete_properties(api, class);
+            completions.extend(complete_classes(api));
+            completions
+        }
+        Context::Value(ref class, ref prop_name) => complete_values(api, class, prop_name),
+    }
+}
+
+fn word_at(text: &str, offset: usize) -> Option<(usize, usize)> {
+    let start = text[..offset].rfind(|c| !is_word_char(c)).map_or(0, |i| i + 1);
+    let end = text[offset..].find(|c| !is_word_char(c)).map_or(text.len(), |i| offset + i);
+
+    if start < end { Some((start, end)) } else { None }
+}
+
+fn tags_line(tags: Vec<&str>) -> String {
+    if tags.is_empty() { String::new() } else { format!("\n\nTags: {}", tags.join(", ")) }
+}
+
+// Markdown describing the class, property or enum member under the cursor.
+pub fn hover(api: &RobloxApi, text: &str, offset: usize) -> Option<(String, Span)> {
+    let (start, end) = word_at(text, offset)?;
+    let word = &text[start..end];
+    let is_property = text[end..].trim_start().starts_with(':');
+
+    let contents = match context_at(text, start) {
+        Context::Body(ref class) if is_property => {
+            let inst = api.get_instance(class.as_ref()?)?;
+            let prop = api.get_property(inst, word)?;
+
+            format!("`{}: {}`{}", prop.get_name(), type_name(prop.get_type()), tags_line(prop.get_tags()))
+        }
+        Context::Value(ref class, ref prop_name) => {
+            let inst = api.get_instance(class.as_ref()?)?;
+            let enum_name = match api.get_property(inst, prop_name)?.get_type() {
+                PropertyType::Enum(ref enum_name) => enum_name,
+                _ => return None,
+            };
+            let value = api.get_enum(enum_name)?.get_member_value(word)?;
+
+            format!("`Enum.{}.{}` = {}", enum_name, word, value)
+        }
+        _ => {
+            let inst = api.get_instance(word)?;
+            let superclass = inst.get_superclass().as_ref()
+                .map_or(String::new(), |superclass| format!(" : {}", superclass));
+
+            format!("`class {}{}`{}", inst.get_name(), superclass, tags_line(inst.get_tags()))
+        }
+    };
+
+    Some((contents, Span::new(start, end)))
+}
+
+// The instance whose class name is at the offset, after its ancestors.
+fn instance_path_at<'a>(instances: &'a [RgInstance], offset: usize, path: &mut Vec<&'a RgInstance>) -> bool {
+    for inst in instances {
+        if !inst.span().contains(offset) {
+            continue;
+        }
+
+        path.push(inst);
+        if inst.class_span().contains(offset) || instance_path_at(inst.children(), offset, path) {
+            return true;
+        }
+        path.pop();
+    }
+
+    false
+}
+
+// Where the rules styling the instance named at the offset were written.
+pub fn styling_rules(nodes: &[RgNode], sheet: &StyleSheet, offset: usize) -> Vec<Span> {
+    let mut path = vec![];
+
+    for node in nodes {
+        if let RgNode::Instance(ref inst) = node {
+            if instance_path_at(::std::slice::from_ref(inst), offset, &mut path) {
+                break;
+            }
+        }
+    }
+
+    sheet.matching_rules(&path).iter().map(|rule| rule.span()).collect()
+}
+
+#[cfg(test)]
+mod can_analyse {
+    use super::*;
+    use rg::parser;
+    use codegen::test_util::test_api;
+
+    // The cursor is where the | is
+    fn at_cursor(text: &str) -> (String, usize) {
+        let offset = text.find('|').unwrap();
+        (text.replacen('|', "", 1), offset)
+    }
+
+    fn labels(text: &str, is_stylesheet: bool) -> Vec<String> {
+        let (text, offset) = at_cursor(text);
+
+        complete(&test_api(), &text, offset, is_stylesheet).into_iter().map(|c| c.label).collect()
+    }
+
+    macro_rules! test_context {
+        ($test_name: ident, $text: expr, $expected: expr) => {
+            #[test]
+            fn $test_name() {
+                let (text, offset) = at_cursor($text);
+                assert_eq!(context_at(&text, offset), $expected);
+            }
+        }
+    }
+
+    fn body(class: &str) -> Context {
+        Context::Body(Some(class.to_owned()))
+    }
+
+    fn value(class: &str, prop: &str) -> Context {
+        Context::Value(Some(class.to_owned()), prop.to_owned())
+    }
+
+    test_context!(top_level, "Frame {}\n|", Context::TopLevel);
+    test_context!(instance_body, "Frame \"Menu\" {\n    |", body("Frame"));
+    test_context!(nested_body, "Frame {\n    TextLabel { Text: \"}\" }\n    ImageLabel { Vis|", body("ImageLabel"));
+    test_context!(after_child, "Frame {\n    TextLabel {}\n    |", body("Frame"));
+    test_context!(property_value, "TextLabel { TextXAlignment: Ce|", value("TextLabel", "TextXAlignment"));
+    test_context!(next_statement, "TextLabel { Size: 1 Te|", body("TextLabel"));
+    test_context!(style_rule, "Frame \"Menu\" TextLabel { Text|", body("TextLabel"));
+    test_context!(commented_braces, "Frame { // }\n    /* TextLabel { */ |", body("Frame"));
+    test_context!(universal_rule, "* { |", Context::Body(None));
+    test_context!(instance_with_id, "Frame \"Menu\" @main_menu {\n    |", body("Frame"));
+    test_context!(reference_value, "Frame @menu {\n    NextSelectionUp: @menu\n    |", body("Frame"));
+
+    #[test]
+    fn complete_properties_of_instances_with_ids() {
+        assert!(labels("TextLabel @title { | }", false).contains(&"TextColor3".to_owned()));
+    }
+
+    #[test]
+    fn complete_classes_at_top_level() {
+        assert!(labels("|", false).contains(&"TextLabel".to_owned()));
+    }
+
+    #[test]
+    fn complete_inherited_properties() {
+        let labels = labels("TextLabel { | }", false);
+
+        assert!(labels.contains(&"TextColor3".to_owned()));
+        assert!(labels.contains(&"Visible".to_owned()));
+        assert!(labels.contains(&"Frame".to_owned()));
+    }
+
+    #[test]
+    fn complete_only_properties_in_rules() {
+        assert!(!labels("TextLabel { | }", true).contains(&"Frame".to_owned()));
+    }
+
+    #[test]
+    fn complete_enum_members() {
+        assert_eq!(labels("TextLabel { TextXAlignment: | }", false), vec!["Center", "Left", "Right"]);
+    }
+
+    #[test]
+    fn complete_booleans() {
+        assert_eq!(labels("Frame { Visible: t| }", false), vec!["true", "false"]);
+    }
+
+    #[test]
+    fn hover_property() {
+        let (text, offset) = at_cursor("TextLabel { TextXAl|ignment: Center }");
+        let (contents, span) = hover(&test_api(), &text, offset).unwrap();
+
+        assert_eq!(contents, "`TextXAlignment: TextXAlignment`");
+        assert_eq!(span, Span::new(12, 26));
+    }
+
+    #[test]
+    fn hover_class() {
+        let (text, offset) = at_cursor("Frame { Text|Label {} }");
+
+        assert_eq!(hover(&test_api(), &text, offset).unwrap().0, "`class TextLabel : GuiObject`");
+    }
+
+    #[test]
+    fn hover_enum_member() {
+        let (text, offset) = at_cursor("TextLabel { TextXAlignment: Ri|ght }");
+
+        assert_eq!(hover(&test_api(), &text, offset).unwrap().0, "`Enum.TextXAlignment.Right` = 1");
+    }
+
+    #[test]
+    fn find_styling_rules() {
+        let api = test_api();
+        let rgss = "TextLabel { Text: \"a\" }\nFrame \"Menu\" TextLabel { TextSize: 2 }\nImageLabel {}";
+        let sheet = parser::parse_stylesheet(rgss).unwrap();
+        let (rg, offset) = at_cursor("Frame \"Menu\" {\n    Text|Label {}\n}");
+        let (nodes, _) = parser::parse_str(&rg, &StyleSheet::default(), &api);
+
+        assert_eq!(styling_rules(&nodes, &sheet, offset), vec![Span::new(0, 23), Span::new(24, 62)]);
+    }
+}
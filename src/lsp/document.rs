@@ -0,0 +1,112 @@
+use std::path::{Path, PathBuf};
+
+// Positions count lines from zero and columns in UTF-16 code units, which is
+// what clients send unless another encoding is negotiated.
+pub fn offset_at(text: &str, line: usize, character: usize) -> usize {
+    let line_start = text.split('\n').take(line)
+        .map(|line| line.len() + 1)
+        .sum::<usize>()
+        .min(text.len());
+
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+
+    text.len()
+}
+
+pub fn position_at(text: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(text.len());
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+    (before.matches('\n').count(), before[line_start..].encode_utf16().count())
+}
+
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte)
+}
+
+// Only file uris name something on disk, Windows drives keep their letter.
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+
+    while i < encoded.len() {
+        let escaped = encoded.get(i + 1..i + 3)
+            .and_then(|hex| ::std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (encoded[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    let path = String::from_utf8(decoded).ok()?;
+    let is_drive = path.len() > 2 && path.as_bytes()[2] == b':';
+
+    Some(PathBuf::from(if is_drive { &path[1..] } else { &path[..] }))
+}
+
+pub fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from(if path.starts_with('/') { "file://" } else { "file:///" });
+
+    for byte in path.bytes() {
+        if is_unreserved(byte) || byte == b':' {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    uri
+}
+
+#[cfg(test)]
+mod can_convert {
+    use super::*;
+
+    #[test]
+    fn positions_to_offsets() {
+        let text = "Frame {\n    Text: \"😀\" Size: 1\n}";
+
+        assert_eq!(offset_at(text, 0, 6), 6);
+        assert_eq!(offset_at(text, 1, 4), 12);
+        assert_eq!(offset_at(text, 1, 14), text.find(" Size").unwrap());
+        assert_eq!(offset_at(text, 1, 99), text.rfind('\n').unwrap());
+        assert_eq!(offset_at(text, 9, 0), text.len());
+    }
+
+    #[test]
+    fn offsets_to_positions() {
+        let text = "Frame {\n    Text: \"😀\" Size: 1\n}";
+
+        assert_eq!(position_at(text, 12), (1, 4));
+        assert_eq!(position_at(text, text.find(" Size").unwrap()), (1, 14));
+    }
+
+    #[test]
+    fn uris_to_paths() {
+        assert_eq!(uri_to_path("file:///home/me/my%20gui/menu.rg"), Some(PathBuf::from("/home/me/my gui/menu.rg")));
+        assert_eq!(uri_to_path("file:///c%3A/gui/menu.rg"), Some(PathBuf::from("c:/gui/menu.rg")));
+        assert_eq!(uri_to_path("untitled:Untitled-1"), None);
+    }
+
+    #[test]
+    fn paths_to_uris() {
+        assert_eq!(path_to_uri(Path::new("/home/me/my gui/menu.rg")), "file:///home/me/my%20gui/menu.rg");
+    }
+}
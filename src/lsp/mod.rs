@@ -0,0 +1,448 @@
+mod transport;
+mod document;
+mod analysis;
+
+use std::io::{self, BufRead, Write};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use serde_json::{self, Value as Json};
+use rg::parser;
+use rg::span::Span;
+use rg::styles::StyleSheet;
+use rg::diagnostics::{Diagnostic, Severity};
+use roblox::RobloxApi;
+use project::{self, Project, has_extension};
+use self::analysis::CompletionKind;
+
+// Answers an editor's requests about the .rg and .rgss files it has open,
+// which are read from the messages rather than disk as they may be unsaved.
+struct Server<'a> {
+    api: &'a RobloxApi,
+    root: Option<PathBuf>,
+    documents: HashMap<String, String>,
+    // Every stylesheet in the workspace by uri and the rules of all of them,
+    // only read again when one of them changes
+    stylesheets: Vec<(String, String)>,
+    styles: StyleSheet,
+}
+
+fn is_stylesheet(uri: &str) -> bool {
+    uri.ends_with(".rgss")
+}
+
+fn range(text: &str, span: Span) -> Json {
+    let (start_line, start_character) = document::position_at(text, span.start());
+    let (end_line, end_character) = document::position_at(text, span.end());
+
+    json!({
+        "start": { "line": start_line, "character": start_character },
+        "end": { "line": end_line, "character": end_character },
+    })
+}
+
+fn to_lsp_diagnostic(text: &str, diagnostic: &Diagnostic) -> Json {
+    let mut message = diagnostic.message().to_owned();
+
+    for note in diagnostic.notes() {
+        message.push_str(&format!("\nnote: {}", note));
+    }
+    if let Some(help) = diagnostic.help() {
+        message.push_str(&format!("\nhelp: {}", help));
+    }
+
+    json!({
+        "range": range(text, diagnostic.span()),
+        "severity": if diagnostic.severity() == Severity::Error { 1 } else { 2 },
+        "code": diagnostic.code(),
+        "source": "rgss",
+        "message": message,
+    })
+}
+
+impl<'a> Server<'a> {
+    fn new(api: &'a RobloxApi) -> Server<'a> {
+        Server { api, root: None, documents: HashMap::new(), stylesheets: vec![], styles: StyleSheet::default() }
+    }
+
+    // Every stylesheet in the workspace in cascade order, with open ones as
+    // they are in the editor.
+    fn read_stylesheets(&self) -> Vec<(String, String)> {
+        let mut sheets: BTreeMap<PathBuf, String> = BTreeMap::new();
+
+        if let Some(project) = self.root.as_ref().and_then(|root| Project::collect(::std::slice::from_ref(root)).ok()) {
+            for path in project.stylesheets() {
+                if let Ok(text) = project::read_file(path) {
+                    sheets.insert(path.clone(), text);
+                }
+            }
+        }
+
+        for (uri, text) in &self.documents {
+            if let Some(path) = document::uri_to_path(uri).filter(|path| has_extension(path, "rgss")) {
+                sheets.insert(path, text.clone());
+            }
+        }
+
+        sheets.into_iter().map(|(path, text)| (document::path_to_uri(&path), text)).collect()
+    }
+
+    // Sheets with syntax errors are left out, their errors are shown in the sheet itself.
+    fn reload_stylesheets(&mut self) {
+        self.stylesheets = self.read_stylesheets();
+        self.styles = StyleSheet::default();
+
+        for (_, text) in &self.stylesheets {
+            if let Ok(sheet) = parser::parse_stylesheet(text) {
+                self.styles.extend(sheet);
+            }
+        }
+    }
+
+    fn publish_diagnostics(&self, uri: &str, styles: &StyleSheet) -> Json {
+        let text = &self.documents[uri];
+        let diagnostics: Vec<Json> = if is_stylesheet(uri) {
+            parser::parse_stylesheet(text).err().iter()
+                .map(|diagnostic| to_lsp_diagnostic(text, diagnostic))
+                .collect()
+        } else {
+            parser::parse_str(text, styles, self.api).1.iter()
+                .map(|diagnostic| to_lsp_diagnostic(text, diagnostic))
+                .collect()
+        };
+
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        })
+    }
+
+    // A stylesheet can change the diagnostics of every source, so all of
+    // them are checked again when one changes.
+    fn changed(&mut self, uri: &str) -> Vec<Json> {
+        if is_stylesheet(uri) {
+            self.reload_stylesheets();
+        }
+
+        self.documents.keys()
+            .filter(|open| *open == uri || (is_stylesheet(uri) && !is_stylesheet(open)))
+            .map(|open| self.publish_diagnostics(open, &self.styles))
+            .collect()
+    }
+
+    // Stylesheets edited outside the editor, or added or deleted, change the
+    // styles of every open source.
+    fn files_changed(&mut self) -> Vec<Json> {
+        self.reload_stylesheets();
+
+        self.documents.keys()
+            .filter(|open| !is_stylesheet(open))
+            .map(|open| self.publish_diagnostics(open, &self.styles))
+            .collect()
+    }
+
+    // The document the request is about and the offset of its position.
+    fn position<'b>(&'b self, params: &Json) -> Option<(&'b str, &'b str, usize)> {
+        let (uri, text) = self.documents.get_key_value(params["textDocument"]["uri"].as_str()?)?;
+        let line = params["position"]["line"].as_u64()? as usize;
+        let character = params["position"]["character"].as_u64()? as usize;
+
+        Some((uri, text, document::offset_at(text, line, character)))
+    }
+
+    fn completion(&self, params: &Json) -> Json {
+        let (uri, text, offset) = match self.position(params) {
+            Some(position) => position,
+            None => return Json::Null,
+        };
+
+        let items: Vec<Json> = analysis::complete(self.api, text, offset, is_stylesheet(uri)).into_iter()
+            .map(|completion| json!({
+                "label": completion.label,
+                "kind": match completion.kind {
+                    CompletionKind::Class => 7,
+                    CompletionKind::Property => 10,
+                    CompletionKind::Value => 20,
+                },
+                "detail": completion.detail,
+            }))
+            .collect();
+
+        Json::Array(items)
+    }
+
+    fn hover(&self, params: &Json) -> Json {
+        self.position(params)
+            .and_then(|(_, text, offset)| {
+                let (contents, span) = analysis::hover(self.api, text, offset)?;
+
+                Some(json!({
+                    "contents": { "kind": "markdown", "value": contents },
+                    "range": range(text, span),
+                }))
+            })
+            .unwrap_or(Json::Null)
+    }
+
+    fn definition(&self, params: &Json) -> Json {
+        let (uri, text, offset) = match self.position(params) {
+            Some(position) => position,
+            None => return Json::Null,
+        };
+
+        if is_stylesheet(uri) {
+            return Json::Null;
+        }
+
+        let (nodes, _) = parser::parse_str(text, &StyleSheet::default(), self.api);
+        let mut locations = vec![];
+
+        for (sheet_uri, sheet_text) in &self.stylesheets {
+            if let Ok(sheet) = parser::parse_stylesheet(sheet_text) {
+                for span in analysis::styling_rules(&nodes, &sheet, offset) {
+                    locations.push(json!({ "uri": sheet_uri, "range": range(sheet_text, span) }));
+                }
+            }
+        }
+
+        Json::Array(locations)
+    }
+
+    // The responses and notifications to send back for a message.
+    fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let params = &message["params"];
+
+        // Replies to requests of ours, such as registering for file changes
+        let method = match message["method"].as_str() {
+            Some(method) => method,
+            None => return vec![],
+        };
+
+        let result = match method {
+            "initialize" => {
+                self.root = params["rootUri"].as_str().and_then(document::uri_to_path);
+                self.reload_stylesheets();
+
+                Some(json!({
+                    "capabilities": {
+                        "textDocumentSync": 1,
+                        "completionProvider": { "triggerCharacters": [":"] },
+                        "hoverProvider": true,
+                        "definitionProvider": true,
+                    },
+                    "serverInfo": { "name": "rgss" },
+                }))
+            }
+            "initialized" => return vec![json!({
+                "jsonrpc": "2.0",
+                "id": "watch-stylesheets",
+                "method": "client/registerCapability",
+                "params": { "registrations": [{
+                    "id": "watch-stylesheets",
+                    "method": "workspace/didChangeWatchedFiles",
+                    "registerOptions": { "watchers": [{ "globPattern": "**/*.rgss" }] },
+                }] },
+            })],
+            "shutdown" => Some(Json::Null),
+            "textDocument/didOpen" | "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_owned();
+
+                // Changes are always sent whole, the last one being the latest
+                let text = params["textDocument"]["text"].as_str()
+                    .or_else(|| params["contentChanges"].as_array()?.last()?["text"].as_str());
+
+                if let Some(text) = text {
+                    self.documents.insert(uri.clone(), text.to_owned());
+                    return self.changed(&uri);
+                }
+                None
+            }
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                self.documents.remove(uri);
+
+                let mut messages = if is_stylesheet(uri) { self.changed(uri) } else { vec![] };
+                messages.push(json!({
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": { "uri": uri, "diagnostics": [] },
+                }));
+                return messages;
+            }
+            "workspace/didChangeWatchedFiles" => return self.files_changed(),
+            "textDocument/completion" => Some(self.completion(params)),
+            "textDocument/hover" => Some(self.hover(params)),
+            "textDocument/definition" => Some(self.definition(params)),
+            _ => None,
+        };
+
+        match (message.get("id"), result) {
+            (Some(id), Some(result)) => vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })],
+            (Some(id), None) => vec![json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": format!("Unknown method {}", method) },
+            })],
+            (None, _) => vec![],
+        }
+    }
+}
+
+// Answers messages until the input ends or the editor says to exit. A body
+// which isn't JSON can't be answered by id, so gets a parse error without one.
+fn run<R: BufRead, W: Write>(server: &mut Server, input: &mut R, output: &mut W) -> Result<(), String> {
+    while let Some(body) = transport::read_body(input)? {
+        let replies = match serde_json::from_slice::<Json>(&body) {
+            Ok(ref message) if message["method"] == "exit" => break,
+            Ok(message) => server.handle(&message),
+            Err(e) => vec![json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": -32700, "message": format!("Invalid message: {}", e) },
+            })],
+        };
+
+        for reply in replies {
+            transport::write_message(output, &reply)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Speaks the language server protocol over stdin and stdout.
+pub fn serve(api: &RobloxApi) -> Result<(), String> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+
+    run(&mut Server::new(api), &mut stdin.lock(), &mut stdout.lock())
+}
+
+#[cfg(test)]
+mod can_serve {
+    use super::*;
+    use std::fs;
+    use std::io::Cursor;
+    use codegen::test_util::test_api;
+
+    fn open(server: &mut Server, uri: &str, text: &str) -> Vec<Json> {
+        server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": uri, "languageId": "rg", "version": 1, "text": text } },
+        }))
+    }
+
+    #[test]
+    fn publish_diagnostics_on_open() {
+        let api = test_api();
+        let mut server = Server::new(&api);
+        let messages = open(&mut server, "file:///gui/menu.rg", "Frame {\n    Sise: 1\n}");
+
+        let diagnostic = &messages[0]["params"]["diagnostics"][0];
+        assert_eq!(diagnostic["code"], "E0004");
+        assert_eq!(diagnostic["range"], json!({
+            "start": { "line": 1, "character": 4 },
+            "end": { "line": 1, "character": 8 },
+        }));
+        assert!(diagnostic["message"].as_str().unwrap().ends_with("help: did you mean `Size`?"));
+    }
+
+    #[test]
+    fn restyle_open_sources() {
+        let api = test_api();
+        let mut server = Server::new(&api);
+        open(&mut server, "file:///gui/menu.rg", "Frame {}");
+        let messages = open(&mut server, "file:///gui/theme.rgss", "Frame { Visible: 1 }");
+
+        assert_eq!(messages.len(), 2);
+
+        let source = messages.iter().find(|m| m["params"]["uri"] == "file:///gui/menu.rg").unwrap();
+        assert_eq!(source["params"]["diagnostics"][0]["code"], "E0005");
+    }
+
+    #[test]
+    fn answer_requests() {
+        let api = test_api();
+        let mut server = Server::new(&api);
+        open(&mut server, "file:///gui/menu.rg", "Frame {\n    TextLabel {}\n}");
+        open(&mut server, "file:///gui/theme.rgss", "Frame TextLabel { TextSize: 2 }");
+
+        let reply = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "textDocument/definition",
+            "params": { "textDocument": { "uri": "file:///gui/menu.rg" }, "position": { "line": 1, "character": 6 } },
+        }));
+
+        assert_eq!(reply[0]["id"], 3);
+        assert_eq!(reply[0]["result"][0]["uri"], "file:///gui/theme.rgss");
+    }
+
+    #[test]
+    fn reload_stylesheets_only_when_they_change() {
+        let dir = ::std::env::temp_dir().join(format!("rgss-lsp-styles-{}", ::std::process::id()));
+        project::write_file(&dir.join("theme.rgss"), b"Frame { Visible: 1 }").unwrap();
+
+        let api = test_api();
+        let mut server = Server::new(&api);
+        server.handle(&json!({
+            "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "rootUri": document::path_to_uri(&dir) },
+        }));
+        open(&mut server, "file:///gui/menu.rg", "Frame {}");
+
+        project::write_file(&dir.join("theme.rgss"), b"Frame { Visible: true }").unwrap();
+        let edited = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didChange",
+            "params": { "textDocument": { "uri": "file:///gui/menu.rg" }, "contentChanges": [{ "text": "Frame { }" }] },
+        }));
+        let watched = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "workspace/didChangeWatchedFiles",
+            "params": { "changes": [{ "uri": document::path_to_uri(&dir.join("theme.rgss")), "type": 2 }] },
+        }));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(edited[0]["params"]["diagnostics"][0]["code"], "E0005");
+        assert_eq!(watched[0]["params"]["diagnostics"], json!([]));
+    }
+
+    #[test]
+    fn register_for_stylesheet_changes() {
+        let api = test_api();
+        let mut server = Server::new(&api);
+        let request = server.handle(&json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }));
+
+        assert_eq!(request[0]["method"], "client/registerCapability");
+        assert!(server.handle(&json!({ "jsonrpc": "2.0", "id": request[0]["id"], "result": null })).is_empty());
+    }
+
+    #[test]
+    fn keep_serving_after_invalid_json() {
+        let api = test_api();
+        let mut input = Cursor::new("Content-Length: 5\r\n\r\n{id:}\
+            Content-Length: 44\r\n\r\n{\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"shutdown\"}");
+        let mut output = vec![];
+
+        run(&mut Server::new(&api), &mut input, &mut output).unwrap();
+
+        let mut output = Cursor::new(output);
+        let replies: Vec<Json> = (0..2)
+            .map(|_| serde_json::from_slice(&transport::read_body(&mut output).unwrap().unwrap()).unwrap())
+            .collect();
+
+        assert_eq!(replies[0]["id"], Json::Null);
+        assert_eq!(replies[0]["error"]["code"], -32700);
+        assert_eq!(replies[1]["id"], 2);
+        assert_eq!(replies[1]["result"], Json::Null);
+    }
+
+    #[test]
+    fn reject_unknown_requests() {
+        let api = test_api();
+        let reply = Server::new(&api).handle(&json!({ "jsonrpc": "2.0", "id": 1, "method": "workspace/symbol" }));
+
+        assert_eq!(reply[0]["error"]["code"], -32601);
+    }
+}
@@ -0,0 +1,67 @@
+use std::io::{BufRead, Write};
+use serde_json::Value as Json;
+
+// Reads the body of the next message, which should be JSON, skipping the
+// headers before it that give its length. None once the client closes the stream.
+pub fn read_body<R: BufRead>(reader: &mut R) -> Result<Option<Vec<u8>>, String> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).map_err(|e| e.to_string())? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = Some(value.trim().parse::<usize>()
+                .map_err(|_| format!("Invalid header {}", header))?);
+        }
+    }
+
+    let mut body = vec![0; length.ok_or("Message without a Content-Length header")?];
+    reader.read_exact(&mut body).map_err(|e| e.to_string())?;
+
+    Ok(Some(body))
+}
+
+pub fn write_message<W: Write>(writer: &mut W, message: &Json) -> Result<(), String> {
+    let body = message.to_string();
+
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+        .and_then(|_| writer.flush())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod can_frame {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn read_bodies_in_turn() {
+        let mut input = Cursor::new(
+            "Content-Length: 8\r\n\r\n{\"id\":1}Content-Length: 8\r\nContent-Type: utf-8\r\n\r\n{\"id\":2}");
+
+        assert_eq!(read_body(&mut input), Ok(Some(b"{\"id\":1}".to_vec())));
+        assert_eq!(read_body(&mut input), Ok(Some(b"{\"id\":2}".to_vec())));
+        assert_eq!(read_body(&mut input), Ok(None));
+    }
+
+    #[test]
+    fn reject_missing_length() {
+        assert!(read_body(&mut Cursor::new("Content-Type: utf-8\r\n\r\n{}")).is_err());
+    }
+
+    #[test]
+    fn write_length_in_bytes() {
+        let mut output = vec![];
+        write_message(&mut output, &Json::String("é".to_owned())).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "Content-Length: 4\r\n\r\n\"é\"");
+    }
+}
@@ -1,41 +1,109 @@
 #[macro_use]
 extern crate serde_derive;
+#[macro_use]
 extern crate serde_json;
+extern crate lz4_flex;
+extern crate xml;
 
 mod rg;
 mod roblox;
+mod codegen;
+mod import;
+mod cli;
+mod project;
+mod watch;
+mod config;
+mod lsp;
+
+use std::env;
+use std::fs;
+use std::ffi::OsStr;
+use std::process;
+use std::path::Path;
+use cli::{Command, Options};
+use config::Config;
+use codegen::Format;
+use project::Project;
+use roblox::{ApiMode, RobloxApi};
+
+fn load_api(api_mode: ApiMode) -> Result<RobloxApi, String> {
+    let config = Config::find(&env::current_dir().map_err(|e| e.to_string())?)?;
 
-use rg::parser;
+    roblox::load(api_mode, config.api())
+}
 
-fn compile_str(contents: &str) {
-    match parser::parse_str(contents) {
-        Ok(api) => println!("{}", serde_json::to_string(&api).unwrap()),
-        Err(error) => println!("Error when compiling {}", error),
+fn compile_all(options: &Options, project: &Project, output: Option<&Path>, format: Option<Format>) -> Result<(), String> {
+    if project.sources().is_empty() {
+        return Err("No .rg files to compile".to_owned());
+    }
+
+    let api = load_api(options.api_mode)?;
+    let styles = project.load_styles(options.message_format)?;
+
+    match project::build_sources(&api, &styles, project, project.sources(), output, format, options.message_format) {
+        0 => Ok(()),
+        failures => Err(format!("{} of {} files failed to compile", failures, project.sources().len())),
     }
 }
 
-fn main() {
-    let contents = r#"ScreenGui {
-    Name: "WelcomeGui"
-    Enabled: true
-
-    TextLabel {
-        Text: "Welcome"
-        BackgroundColor3: #FF00FF
-        TextStrokeTransparency: 0.2
-        BorderSizePixel: 0
+fn format_all(options: &Options, project: &Project, check: bool) -> Result<(), String> {
+    if project.sources().is_empty() {
+        return Err("No .rg files to format".to_owned());
     }
 
-    TextButton "Continue" {
-        Text: "Continue"
-        BackgroundTransparency: 1
-        TextXAlignment: Center
-        TextColor3: RGB(255, 20, 120)
+    let config = Config::find(&env::current_dir().map_err(|e| e.to_string())?)?;
+
+    match project::format_sources(project, config.sort_properties(), check, options.message_format) {
+        0 => Ok(()),
+        failures if check => Err(format!("{} of {} files are not formatted", failures, project.sources().len())),
+        failures => Err(format!("{} of {} files could not be formatted", failures, project.sources().len())),
     }
-}"#;
+}
+
+fn import(options: &Options, input: &Path, output: Option<&Path>) -> Result<(), String> {
+    let api = load_api(options.api_mode)?;
 
-    compile_str(contents);
+    let source = if input.extension() == Some(OsStr::new("rbxm")) {
+        let bytes = fs::read(input).map_err(|e| format!("{}: {}", input.display(), e))?;
+        import::decompile_rbxm(&api, &bytes)
+    } else {
+        import::decompile_rbxmx(&api, &project::read_file(input)?)
+    };
 
+    let (source, diagnostics) = source.map_err(|e| format!("{}: {}", input.display(), e))?;
+    let path = output.map_or_else(|| input.with_extension("rg"), |path| path.to_owned());
 
+    project::write_file(&path, source.as_bytes())?;
 
+    for diagnostic in diagnostics.iter() {
+        diagnostic.emit(options.message_format, &path.display().to_string(), &source);
+    }
+
+    Ok(())
+}
+
+fn run(options: Options) -> Result<(), String> {
+    match options.command {
+        Command::Build { ref inputs, ref output, format } =>
+            compile_all(&options, &Project::collect(inputs)?, output.as_deref(), Some(format)),
+        Command::Check { ref inputs } => compile_all(&options, &Project::collect(inputs)?, None, None),
+        Command::Watch { ref inputs, ref output, format } =>
+            watch::watch(&load_api(options.api_mode)?, inputs, output.as_deref(), format, options.message_format),
+        Command::Import { ref input, ref output } => import(&options, input, output.as_deref()),
+        Command::Fmt { ref inputs, check } => format_all(&options, &Project::collect(inputs)?, check),
+        Command::Lsp => lsp::serve(&load_api(options.api_mode)?),
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if let Err(error) = cli::parse(&args).and_then(run) {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
 }
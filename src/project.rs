@@ -0,0 +1,282 @@
+use std::fs;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{self, Path, PathBuf};
+use rg::{parser, printer};
+use rg::values::RgNode;
+use rg::styles::StyleSheet;
+use rg::diagnostics::{MessageFormat, Severity};
+use roblox::RobloxApi;
+use codegen::{self, Format};
+
+// The .rg sources and .rgss stylesheets named on the command line.
+#[derive(PartialEq, Debug, Default)]
+pub struct Project {
+    sources: Vec<PathBuf>,
+    stylesheets: Vec<PathBuf>,
+    // Each source's path within the directory it was found in, or its file name
+    // when it was named itself
+    relative_paths: HashMap<PathBuf, PathBuf>,
+}
+
+pub fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension() == Some(OsStr::new(extension))
+}
+
+pub fn read_file(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub fn write_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+
+    fs::write(path, contents).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+impl Project {
+    // Directories are searched recursively, files within them are taken in
+    // name order so stylesheets cascade the same way on every machine.
+    pub fn collect(inputs: &[PathBuf]) -> Result<Project, String> {
+        let mut project = Project::default();
+
+        for input in inputs {
+            if input.is_dir() {
+                project.add_dir(input, input)?;
+            } else if !input.exists() {
+                return Err(format!("{}: No such file or directory", input.display()));
+            } else if !project.add_file(input, Path::new(input.file_name().unwrap_or_default())) {
+                return Err(format!("{}: Expected a .rg or .rgss file", input.display()));
+            }
+        }
+
+        Ok(project)
+    }
+
+    fn add_file(&mut self, path: &Path, relative_path: &Path) -> bool {
+        if has_extension(path, "rg") {
+            self.sources.push(path.to_owned());
+            self.relative_paths.insert(path.to_owned(), relative_path.to_owned());
+        } else if has_extension(path, "rgss") {
+            self.stylesheets.push(path.to_owned());
+        } else {
+            return false;
+        }
+
+        true
+    }
+
+    fn add_dir(&mut self, root: &Path, dir: &Path) -> Result<(), String> {
+        let mut entries = fs::read_dir(dir)
+            .and_then(|entries| entries.map(|entry| entry.map(|e| e.path())).collect::<Result<Vec<PathBuf>, _>>())
+            .map_err(|e| format!("{}: {}", dir.display(), e))?;
+
+        entries.sort();
+
+        for entry in entries {
+            if entry.is_dir() {
+                self.add_dir(root, &entry)?;
+            } else {
+                let relative_path = entry.strip_prefix(root).unwrap_or(&entry).to_owned();
+                self.add_file(&entry, &relative_path);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn sources(&self) -> &Vec<PathBuf> {
+        &self.sources
+    }
+
+    pub fn relative_path<'a>(&'a self, source: &'a Path) -> &'a Path {
+        self.relative_paths.get(source).map_or(source, |path| path.as_path())
+    }
+
+    pub fn stylesheets(&self) -> &Vec<PathBuf> {
+        &self.stylesheets
+    }
+
+    // Later stylesheets win ties against earlier ones.
+    pub fn load_styles(&self, message_format: MessageFormat) -> Result<StyleSheet, String> {
+        let mut styles = StyleSheet::default();
+
+        for path in &self.stylesheets {
+            let text = read_file(path)?;
+            let sheet = parser::parse_stylesheet(&text)
+                .map_err(|diagnostic| {
+                    diagnostic.emit(message_format, &path.display().to_string(), &text);
+                    format!("could not load {} due to a syntax error", path.display())
+                })?;
+
+            styles.extend(sheet);
+        }
+
+        Ok(styles)
+    }
+}
+
+// Prints every diagnostic found in the source, failing if any were errors.
+pub fn compile_file(api: &RobloxApi, styles: &StyleSheet, path: &Path, message_format: MessageFormat)
+                    -> Result<Vec<RgNode>, String> {
+    let text = read_file(path)?;
+    let (nodes, diagnostics) = parser::parse_str(&text, styles, api);
+
+    for diagnostic in diagnostics.iter() {
+        diagnostic.emit(message_format, &path.display().to_string(), &text);
+    }
+
+    if !diagnostics.has_errors() {
+        return Ok(nodes);
+    }
+
+    match diagnostics.count(Severity::Error) {
+        1 => Err(format!("could not compile {} due to 1 error", path.display())),
+        errors => Err(format!("could not compile {} due to {} errors", path.display(), errors)),
+    }
+}
+
+// Compiles the given sources of the project, writing them out unless only
+// checking. Errors are reported as they happen and the failures counted.
+pub fn build_sources(api: &RobloxApi, styles: &StyleSheet, project: &Project, sources: &[PathBuf],
+                     output: Option<&Path>, format: Option<Format>, message_format: MessageFormat) -> usize {
+    let single_source = project.sources().len() == 1;
+    let mut written: HashMap<PathBuf, &Path> = HashMap::new();
+    let mut failures = 0;
+
+    for source in sources {
+        let result = compile_file(api, styles, source, message_format).and_then(|nodes| match format {
+            Some(format) => {
+                let path = output_path(source, project.relative_path(source), output, single_source, format.extension());
+
+                // Sources from different directories can have the same name
+                if let Some(other) = written.get(&path) {
+                    return Err(format!("{} and {} would both be written to {}",
+                        other.display(), source.display(), path.display()));
+                }
+
+                write_file(&path, &codegen::generate(api, &nodes, format)?)?;
+                written.insert(path, source);
+                Ok(())
+            }
+            None => Ok(()),
+        });
+
+        if let Err(error) = result {
+            eprintln!("error: {}", error);
+            failures += 1;
+        }
+    }
+
+    failures
+}
+
+// Rewrites the sources which aren't laid out as rgss fmt would, or when only
+// checking lists them. Both unformatted sources and unparsable ones are counted.
+pub fn format_sources(project: &Project, sort_properties: bool, check: bool, message_format: MessageFormat) -> usize {
+    let mut failures = 0;
+
+    for source in project.sources() {
+        let result = read_file(source).and_then(|text| {
+            let formatted = printer::format_str(&text, sort_properties).map_err(|diagnostic| {
+                diagnostic.emit(message_format, &source.display().to_string(), &text);
+                format!("could not format {} due to a syntax error", source.display())
+            })?;
+
+            match (formatted == text, check) {
+                (true, _) => Ok(()),
+                (false, true) => Err(format!("{} is not formatted", source.display())),
+                (false, false) => write_file(source, formatted.as_bytes()),
+            }
+        });
+
+        if let Err(error) = result {
+            eprintln!("error: {}", error);
+            failures += 1;
+        }
+    }
+
+    failures
+}
+
+fn is_dir_path(path: &Path) -> bool {
+    path.is_dir() || path.to_string_lossy().ends_with(path::is_separator)
+}
+
+// Where a compiled source is written, see the usage text for the rules. In an
+// output directory sources keep their path within the directory they came from.
+pub fn output_path(source: &Path, relative_path: &Path, output: Option<&Path>, single_source: bool, extension: &str) -> PathBuf {
+    match output {
+        Some(file) if single_source && !is_dir_path(file) => file.to_owned(),
+        Some(dir) => dir.join(relative_path).with_extension(extension),
+        None => source.with_extension(extension),
+    }
+}
+
+#[cfg(test)]
+mod can_locate {
+    use super::*;
+
+    macro_rules! test_output_path {
+        ($test_name: ident, $source: expr, $relative: expr, $output: expr, $single: expr, $expected: expr) => {
+            #[test]
+            fn $test_name() {
+                let output: Option<&str> = $output;
+                let path = output_path(Path::new($source), Path::new($relative), output.map(Path::new), $single, "lua");
+
+                assert_eq!(path, PathBuf::from($expected));
+            }
+        }
+    }
+
+    test_output_path!(next_to_source, "gui/menu.rg", "menu.rg", None, true, "gui/menu.lua");
+    test_output_path!(named_output, "gui/menu.rg", "menu.rg", Some("out/menu.client.lua"), true, "out/menu.client.lua");
+    test_output_path!(output_dir, "gui/menu.rg", "menu.rg", Some("out"), false, "out/menu.lua");
+    test_output_path!(trailing_separator, "gui/menu.rg", "menu.rg", Some("out/"), true, "out/menu.lua");
+    test_output_path!(keep_subdirectories, "gui/shop/menu.rg", "shop/menu.rg", Some("out"), false, "out/shop/menu.lua");
+
+    #[test]
+    fn sorted_dir_contents() {
+        let dir = ::std::env::temp_dir().join(format!("rgss-collect-{}", ::std::process::id()));
+        for file in &["b.rg", "a.rg", "styles/theme.rgss", "notes.txt"] {
+            write_file(&dir.join(file), b"").unwrap();
+        }
+
+        let project = Project::collect(::std::slice::from_ref(&dir));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let project = project.unwrap();
+        assert_eq!(project.sources, vec![dir.join("a.rg"), dir.join("b.rg")]);
+        assert_eq!(project.stylesheets, vec![dir.join("styles/theme.rgss")]);
+    }
+
+    #[test]
+    fn same_names_in_different_dirs() {
+        use codegen::test_util::test_api;
+
+        let dir = ::std::env::temp_dir().join(format!("rgss-same-names-{}", ::std::process::id()));
+        for file in &["gui/a/menu.rg", "gui/b/menu.rg"] {
+            write_file(&dir.join(file), b"Frame {}").unwrap();
+        }
+
+        let build = |inputs: &[PathBuf]| {
+            let project = Project::collect(inputs).unwrap();
+            build_sources(&test_api(), &StyleSheet::default(), &project, project.sources(), Some(&dir.join("out")),
+                Some(Format::Lua), MessageFormat::Human)
+        };
+
+        let from_dir = build(&[dir.join("gui")]);
+        let outputs = (dir.join("out/a/menu.lua").is_file(), dir.join("out/b/menu.lua").is_file());
+        let from_files = build(&[dir.join("gui/a/menu.rg"), dir.join("gui/b/menu.rg")]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!((from_dir, outputs), (0, (true, true)));
+        assert_eq!(from_files, 1);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(Project::collect(&[PathBuf::from("Cargo.toml")]).is_err());
+    }
+}
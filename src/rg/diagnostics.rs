@@ -0,0 +1,317 @@
+use std::fmt;
+use serde_json;
+use rg::span::{self, Span};
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+// Codes stay the same between releases so they can be searched for.
+pub mod codes {
+    pub const SYNTAX_ERROR: &str = "E0001";
+    pub const UNKNOWN_CLASS: &str = "E0002";
+    pub const NOT_CREATABLE: &str = "E0003";
+    pub const UNKNOWN_PROPERTY: &str = "E0004";
+    pub const MISMATCHED_VALUE: &str = "E0005";
+    pub const READONLY_PROPERTY: &str = "E0006";
+    pub const INVALID_VALUE: &str = "E0008";
+    pub const UNSUPPORTED_TYPE: &str = "E0009";
+    pub const DUPLICATE_ID: &str = "E0010";
+    pub const UNKNOWN_REFERENCE: &str = "E0011";
+    pub const DEPRECATED_PROPERTY: &str = "W0001";
+    pub const DUPLICATE_PROPERTY: &str = "W0002";
+    pub const DROPPED_PROPERTY: &str = "W0003";
+}
+
+// How diagnostics are printed: rendered with a snippet of the source for
+// people, or one JSON object per line for editors and CI.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum MessageFormat {
+    Human,
+    Json,
+}
+
+impl MessageFormat {
+    pub fn parse(name: &str) -> Result<MessageFormat, String> {
+        match name {
+            "human" => Ok(MessageFormat::Human),
+            "json" => Ok(MessageFormat::Json),
+            _ => Err(format!("Unknown message format {}, expected human or json", name)),
+        }
+    }
+}
+
+// A name that could replace the span to fix the problem. Without a span the
+// fix has to be made somewhere else, such as in the stylesheet that set it.
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct Suggestion {
+    replacement: String,
+    span: Option<Span>,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    severity: Severity,
+    code: &'static str,
+    message: String,
+    span: Span,
+    notes: Vec<String>,
+    suggestions: Vec<Suggestion>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, message: String, span: Span) -> Diagnostic {
+        Diagnostic { severity: Severity::Error, code, message, span, notes: vec![], suggestions: vec![] }
+    }
+
+    pub fn warning(code: &'static str, message: String, span: Span) -> Diagnostic {
+        Diagnostic { severity: Severity::Warning, code, message, span, notes: vec![], suggestions: vec![] }
+    }
+
+    pub fn with_span(self, span: Span) -> Diagnostic {
+        Diagnostic { span, ..self }
+    }
+
+    pub fn with_note(mut self, note: String) -> Diagnostic {
+        self.notes.push(note);
+        self
+    }
+
+    pub fn with_suggestions<S: Into<String>>(mut self, span: Span, replacements: Vec<S>) -> Diagnostic {
+        self.suggestions.extend(replacements.into_iter()
+            .map(|r| Suggestion { replacement: r.into(), span: Some(span) }));
+        self
+    }
+
+    // Keeps the suggested names but not where they go, as they no longer apply to the span.
+    pub fn without_suggestion_spans(mut self) -> Diagnostic {
+        for suggestion in &mut self.suggestions {
+            suggestion.span = None;
+        }
+        self
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn code(&self) -> &str {
+        self.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn notes(&self) -> &Vec<String> {
+        &self.notes
+    }
+
+    // The suggested names as a question, if there are any.
+    pub fn help(&self) -> Option<String> {
+        let names: Vec<String> = self.suggestions.iter()
+            .map(|s| format!("`{}`", s.replacement))
+            .collect();
+
+        match names.len() {
+            0 => None,
+            1 => Some(format!("did you mean {}?", names[0])),
+            _ => Some(format!("did you mean one of {}?", names.join(", "))),
+        }
+    }
+
+    // Prints the diagnostic found in the named source. JSON goes to stdout so
+    // it isn't interleaved with anything else written to stderr.
+    pub fn emit(&self, format: MessageFormat, name: &str, text: &str) {
+        match format {
+            MessageFormat::Human => eprintln!("{}\n", self.render(name, text)),
+            MessageFormat::Json => println!("{}", self.to_json(name, text)),
+        }
+    }
+
+    // The diagnostic as a single line of JSON, with spans as both byte
+    // offsets and 1-based lines and columns.
+    pub fn to_json(&self, name: &str, text: &str) -> String {
+        let diagnostic = JsonDiagnostic {
+            file: name,
+            severity: self.severity,
+            code: self.code,
+            message: &self.message,
+            span: JsonSpan::new(text, self.span),
+            notes: &self.notes,
+            suggestions: self.suggestions.iter()
+                .map(|s| JsonSuggestion {
+                    replacement: &s.replacement,
+                    span: s.span.map(|span| JsonSpan::new(text, span)),
+                })
+                .collect(),
+            rendered: self.render(name, text),
+        };
+
+        serde_json::to_string(&diagnostic).unwrap()
+    }
+
+    // The message with a snippet of the source it was found in.
+    pub fn render(&self, name: &str, text: &str) -> String {
+        let heading = format!("{}[{}]: {}", self.severity, self.code, self.message);
+        let mut rendered = span::render(name, text, self.span, &heading);
+
+        for note in &self.notes {
+            rendered.push_str(&format!("\n  = note: {}", note));
+        }
+
+        if let Some(help) = self.help() {
+            rendered.push_str(&format!("\n  = help: {}", help));
+        }
+
+        rendered
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonSpan {
+    start: usize,
+    end: usize,
+    line: usize,
+    column: usize,
+    end_line: usize,
+    end_column: usize,
+}
+
+impl JsonSpan {
+    fn new(text: &str, span: Span) -> JsonSpan {
+        let (line, column) = span::line_col(text, span.start());
+        let (end_line, end_column) = span::line_col(text, span.end());
+
+        JsonSpan { start: span.start(), end: span.end(), line, column, end_line, end_column }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonSuggestion<'a> {
+    replacement: &'a str,
+    span: Option<JsonSpan>,
+}
+
+#[derive(Serialize)]
+struct JsonDiagnostic<'a> {
+    file: &'a str,
+    severity: Severity,
+    code: &'a str,
+    message: &'a str,
+    span: JsonSpan,
+    notes: &'a [String],
+    suggestions: Vec<JsonSuggestion<'a>>,
+    rendered: String,
+}
+
+// Everything found wrong with a file, so it can all be fixed in one go.
+#[derive(PartialEq, Debug, Default)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    pub fn iter(&self) -> ::std::slice::Iter<'_, Diagnostic> {
+        self.diagnostics.iter()
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.iter().filter(|d| d.severity == severity).count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.count(Severity::Error) > 0
+    }
+}
+
+#[cfg(test)]
+mod can_render {
+    use super::*;
+
+    #[test]
+    fn with_notes() {
+        let diagnostic = Diagnostic::error(codes::MISMATCHED_VALUE, "Bad value".to_owned(), Span::new(8, 12))
+            .with_note("set by style rule #1 `Frame`".to_owned());
+
+        assert_eq!(diagnostic.render("menu.rg", "Frame { Size: 1 }"),
+            "menu.rg:1:9: error[E0005]: Bad value\n  |\n1 | Frame { Size: 1 }\n  |         ^^^^\n  \
+            = note: set by style rule #1 `Frame`");
+    }
+
+    #[test]
+    fn with_suggestions() {
+        let diagnostic = Diagnostic::error(codes::UNKNOWN_CLASS, "Class Fram does not exist".to_owned(), Span::new(0, 4))
+            .with_suggestions(Span::new(0, 4), vec!["Frame"]);
+
+        assert_eq!(diagnostic.render("menu.rg", "Fram {}"),
+            "menu.rg:1:1: error[E0002]: Class Fram does not exist\n  |\n1 | Fram {}\n  | ^^^^\n  \
+            = help: did you mean `Frame`?");
+    }
+
+    #[test]
+    fn with_several_suggestions() {
+        let diagnostic = Diagnostic::error(codes::UNKNOWN_CLASS, "Class Fram does not exist".to_owned(), Span::new(0, 4))
+            .with_suggestions(Span::new(0, 4), vec!["Frame", "Frames"]);
+
+        assert!(diagnostic.render("menu.rg", "Fram {}").ends_with("= help: did you mean one of `Frame`, `Frames`?"));
+    }
+
+    #[test]
+    fn as_json() {
+        let diagnostic = Diagnostic::error(codes::UNKNOWN_PROPERTY, "No Sise".to_owned(), Span::new(12, 16))
+            .with_suggestions(Span::new(12, 16), vec!["Size"]);
+        let json: serde_json::Value = serde_json::from_str(&diagnostic.to_json("menu.rg", "Frame {\n    Sise: 1\n}")).unwrap();
+
+        assert_eq!(json["file"], "menu.rg");
+        assert_eq!(json["severity"], "error");
+        assert_eq!(json["code"], "E0004");
+        assert_eq!(json["message"], "No Sise");
+        assert_eq!(json["span"].to_string(), r#"{"column":5,"end":16,"end_column":9,"end_line":2,"line":2,"start":12}"#);
+        assert_eq!(json["suggestions"][0]["replacement"], "Size");
+        assert_eq!(json["suggestions"][0]["span"]["start"], 12);
+    }
+
+    #[test]
+    fn unplaced_suggestions_as_json() {
+        let diagnostic = Diagnostic::error(codes::UNKNOWN_PROPERTY, "No Sise".to_owned(), Span::new(0, 5))
+            .with_suggestions(Span::new(12, 16), vec!["Size"])
+            .without_suggestion_spans();
+        let json: serde_json::Value = serde_json::from_str(&diagnostic.to_json("menu.rg", "Frame {}")).unwrap();
+
+        assert!(json["suggestions"][0]["span"].is_null());
+    }
+
+    #[test]
+    fn count_severities() {
+        let mut diagnostics = Diagnostics::default();
+        diagnostics.push(Diagnostic::warning(codes::DEPRECATED_PROPERTY, String::new(), Span::default()));
+
+        assert!(!diagnostics.has_errors());
+
+        diagnostics.push(Diagnostic::error(codes::UNKNOWN_CLASS, String::new(), Span::default()));
+
+        assert!(diagnostics.has_errors());
+        assert_eq!(diagnostics.count(Severity::Warning), 1);
+    }
+}
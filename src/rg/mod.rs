@@ -1,5 +1,10 @@
 pub mod parser;
 pub mod values;
+pub mod styles;
+pub mod printer;
+pub mod span;
+pub mod diagnostics;
+mod suggestions;
 
 mod semantics;
 
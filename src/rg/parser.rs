@@ -3,36 +3,75 @@ mod atoms {
 }
 
 use rg::values::RgNode;
-use rg::semantics::SemanticsChecker;
+use rg::styles::StyleSheet;
+use rg::semantics::{self, SemanticsChecker};
+use rg::span::Span;
+use rg::diagnostics::{codes, Diagnostic, Diagnostics};
+use roblox::RobloxApi;
+
+fn syntax_error(error: &atoms::ParseError) -> Diagnostic {
+    let mut expected: Vec<&str> = error.expected.iter().cloned().collect();
+    expected.sort();
+
+    Diagnostic::error(codes::SYNTAX_ERROR,
+        format!("Expected one of {}", expected.join(", ")),
+        Span::new(error.offset, error.offset))
+}
 
-pub type RgParserResult = Result<Vec<RgNode>, String>;
+pub fn parse_stylesheet(contents: &str) -> Result<StyleSheet, Diagnostic> {
+    atoms::stylesheet(contents)
+        .map(StyleSheet::new)
+        .map_err(|e| syntax_error(&e))
+}
 
-pub fn parse_str(contents: &str) -> RgParserResult {
-    let nodes = atoms::file(contents)
-        .map_err(|e| e.to_string())?;
+// The nodes as written, without styles applied or being checked, along with
+// the comments after the last of them.
+pub fn parse_file(contents: &str) -> Result<(Vec<RgNode>, Vec<String>), Diagnostic> {
+    atoms::file(contents).map_err(|e| syntax_error(&e))
+}
 
-    let semantics_checker = SemanticsChecker::new()?;
+// Returns no nodes when the file can't be parsed, otherwise all of them
+// along with every problem found in them.
+pub fn parse_str(contents: &str, styles: &StyleSheet, api: &RobloxApi) -> (Vec<RgNode>, Diagnostics) {
+    let mut diagnostics = Diagnostics::default();
+
+    let mut nodes = match parse_file(contents) {
+        Ok((nodes, _)) => nodes,
+        Err(diagnostic) => {
+            diagnostics.push(diagnostic);
+            return (vec![], diagnostics);
+        }
+    };
+
+    let ids = semantics::collect_ids(&nodes, &mut diagnostics);
+    let semantics_checker = SemanticsChecker::new(api).with_ids(ids);
+
+    for node in &mut nodes {
+        if let RgNode::Instance(ref mut inst) = node {
+            styles.apply(inst);
+            semantics_checker.resolve_enums(inst);
+        }
+    }
 
     for node in &nodes {
         if let RgNode::Instance(ref inst) = node {
-            semantics_checker.check_instance(inst)?;
-        } else {
-            return Err(format!("Unexpected node encounted when parsing {:?}", node));
+            semantics_checker.check_instance(inst, &mut diagnostics);
         }
     }
 
-    Ok(nodes)
+    (nodes, diagnostics)
 }
 
 #[cfg(test)]
 mod can_parse {
-    use rg::values::{Value, RgProperty as Property, RgNode};
+    use rg::values::{Value, EnumMember, RgProperty as Property, RgNode};
+    use rg::span::Span;
     use super::atoms;
 
     fn check_equality_of_value(parsed: Result<Value, atoms::ParseError>, expected: Value) {
         match parsed {
             Ok(v) => assert_eq!(v, expected),
-            Err(e) => panic!(format!("Expected result {:?} but got result {:?}", expected, e))
+            Err(e) => panic!("Expected result {:?} but got result {:?}", expected, e)
         }
     }
 
@@ -234,11 +273,229 @@ mod can_parse {
         test_property_assignment!(has_string_value, "Text:\"Hi there\"",
             "Text", Value::StringLiteral("Hi there".to_owned()));
 
+        test_property_assignment!(has_udim2_value, "Size: UDim2(0.5, -10, 1, 0)",
+            "Size", Value::UDim2(0.5, -10.0, 1.0, 0.0));
+
+        test_property_assignment!(has_udim_value, "CornerRadius: UDim ( 0 , 8 )",
+            "CornerRadius", Value::UDim(0.0, 8.0));
+
+        test_property_assignment!(has_vector2_value, "AnchorPoint: Vector2(0.5, 0.5)",
+            "AnchorPoint", Value::Vector2(0.5, 0.5));
+
+        test_property_assignment!(has_vector3_value, "Position: Vector3(1, -2.5, 3)",
+            "Position", Value::Vector3(1.0, -2.5, 3.0));
+
+        test_property_assignment!(has_rect_value, "SliceCenter: Rect(10, 10, 20, 20)",
+            "SliceCenter", Value::Rect(10.0, 10.0, 20.0, 20.0));
+
+        test_property_assignment!(has_number_sequence_value, "Transparency: NumberSequence(0: 1, 0.5 : 0.2, 1: 0)",
+            "Transparency", Value::NumberSequence(vec![(0.0, 1.0), (0.5, 0.2), (1.0, 0.0)]));
+
+        test_property_assignment!(has_color_sequence_value, "Color: ColorSequence(0: #FF0000, 1: RGB(0, 0, 255))",
+            "Color", Value::ColorSequence(vec![(0.0, (255, 0, 0)), (1.0, (0, 0, 255))]));
+
+        test_property_assignment!(has_number_range_value, "Lifetime: NumberRange(1, 2.5)",
+            "Lifetime", Value::NumberRange(1.0, 2.5));
+
+        test_property_assignment!(has_single_number_range_value, "Lifetime: NumberRange(3)",
+            "Lifetime", Value::NumberRange(3.0, 3.0));
+
+        test_property_assignment!(has_enum_value, "TextXAlignment: Center",
+            "TextXAlignment", Value::EnumMember(EnumMember::new("Center".to_owned())));
+
+        test_property_assignment!(has_qualified_enum_value, "TextXAlignment: Enum.TextXAlignment.Center",
+            "TextXAlignment", Value::EnumMember(EnumMember::qualified("TextXAlignment".to_owned(), "Center".to_owned())));
+
         test_property_assignment!(padding_between_text, "   Text: \n \" Hi \n there \"   ",
             "Text", Value::StringLiteral(" Hi \n there ".to_owned()));
 
+        #[test]
+        fn record_important() {
+            match atoms::prop_assignment("TextSize: 12 ! important").unwrap() {
+                RgNode::Property(prop) => assert!(prop.is_important()),
+                node => panic!("Expected a property but got {:?}", node),
+            }
+        }
+
+        #[test]
+        fn record_spans() {
+            match atoms::prop_assignment("  TextColor3 : RGB(1, 2, 3)  ").unwrap() {
+                RgNode::Property(prop) => {
+                    assert_eq!(prop.name_span(), Span::new(2, 12));
+                    assert_eq!(prop.value_span(), Span::new(15, 27));
+                }
+                node => panic!("Expected a property but got {:?}", node),
+            }
+        }
+
+        test_property_assignment!(has_reference_value, "NextSelectionUp: @title_2",
+                "NextSelectionUp", Value::Reference("title_2".to_owned()));
         test_property_assignment_fail!(no_value, "Text:");
+        test_property_assignment_fail!(reference_without_id, "NextSelectionUp: @");
         test_property_assignment_fail!(no_prop, ":Hi");
+        test_property_assignment_fail!(too_few_components, "AnchorPoint: Vector2(0.5)");
+        test_property_assignment_fail!(too_many_components, "CornerRadius: UDim(0, 8, 1)");
+        test_property_assignment_fail!(partly_qualified_enum_value, "TextXAlignment: Enum.Center");
+        test_property_assignment_fail!(empty_sequence, "Transparency: NumberSequence()");
+        test_property_assignment_fail!(keypoint_without_time, "Transparency: NumberSequence(0: 1, 0)");
+        test_property_assignment_fail!(keypoint_with_number_color, "Color: ColorSequence(0: 1, 1: 0)");
+    }
+
+    mod stylesheets_that {
+        use super::*;
+        use rg::styles::{RgSelector, RgSimpleSelector as Simple};
+
+        fn class(class_name: &str) -> Simple {
+            Simple::new(Some(class_name.to_owned()), None)
+        }
+
+        fn name(name: &str) -> Simple {
+            Simple::new(None, Some(name.to_owned()))
+        }
+
+        macro_rules! test_selector {
+            ($test_name: ident, $text: expr, $parts: expr) => {
+                #[test]
+                fn $test_name() {
+                    assert_eq!(atoms::selector($text).unwrap(), RgSelector::new($parts));
+                }
+            }
+        }
+
+        test_selector!(is_class_name, "TextButton", vec![class("TextButton")]);
+        test_selector!(is_name, "\"Continue\"", vec![name("Continue")]);
+        test_selector!(is_class_and_name, "TextButton \"Continue\"",
+            vec![Simple::new(Some("TextButton".to_owned()), Some("Continue".to_owned()))]);
+        test_selector!(is_descendant, "ScreenGui  Frame TextButton",
+            vec![class("ScreenGui"), class("Frame"), class("TextButton")]);
+        test_selector!(is_named_descendant, "\"Menu\" TextButton",
+            vec![name("Menu"), class("TextButton")]);
+        test_selector!(is_universal, "*", vec![Simple::new(None, None)]);
+        test_selector!(is_universal_descendant, "Frame *", vec![class("Frame"), Simple::new(None, None)]);
+
+        #[test]
+        fn has_several_rules() {
+            let rules = atoms::stylesheet(r#"
+                TextButton {
+                    Font: Arial;
+                    TextColor3: #FFFFFF
+                }
+
+                "Continue" { Text: "Continue" }
+            "#).unwrap();
+
+            assert_eq!(rules.len(), 2);
+            assert_eq!(rules[0].properties(), &vec![
+                Property::new("Font".to_owned(), Value::EnumMember(EnumMember::new("Arial".to_owned()))),
+                Property::new("TextColor3".to_owned(), Value::Color(255, 255, 255))]);
+            assert_eq!(rules[1].selector(), &RgSelector::new(vec![name("Continue")]));
+        }
+
+        #[test]
+        #[should_panic]
+        fn nested_instance_in_rule() {
+            atoms::stylesheet("Frame { TextButton {} }").unwrap();
+        }
+    }
+
+    mod styles_applied_that {
+        use super::*;
+        use rg::values::{RgInstance, PropertyOrigin};
+        use rg::styles::StyleSheet;
+
+        fn styled(rg: &str, rgss: &str) -> RgInstance {
+            let sheet = StyleSheet::new(atoms::stylesheet(rgss).unwrap());
+
+            match atoms::file(rg).unwrap().0.remove(0) {
+                RgNode::Instance(mut inst) => { sheet.apply(&mut inst); inst },
+                node => panic!("Expected an instance but got {:?}", node),
+            }
+        }
+
+        #[test]
+        fn match_class_name() {
+            let inst = styled("Frame { TextLabel {} }", "TextLabel { Text: \"Hi\" }");
+
+            assert_eq!(inst.get_prop_value("Text"), None);
+            assert_eq!(inst.children()[0].get_prop_value("Text"),
+                Some(&Value::StringLiteral("Hi".to_owned())));
+        }
+
+        #[test]
+        fn match_name() {
+            let inst = styled("Frame { TextButton \"Play\" {} TextButton \"Quit\" {} }",
+                "\"Quit\" { Visible: false }");
+
+            assert_eq!(inst.children()[0].get_prop_value("Visible"), None);
+            assert_eq!(inst.children()[1].get_prop_value("Visible"), Some(&Value::Boolean(false)));
+        }
+
+        #[test]
+        fn match_descendants_only() {
+            let inst = styled("ScreenGui { TextLabel {} Frame { Frame { TextLabel {} } } }",
+                "ScreenGui Frame TextLabel { TextSize: 20 }");
+
+            assert_eq!(inst.children()[0].get_prop_value("TextSize"), None);
+            assert_eq!(inst.children()[1].children()[0].children()[0].get_prop_value("TextSize"),
+                Some(&Value::Number(20f64)));
+        }
+
+        #[test]
+        fn keep_inline_properties() {
+            let inst = styled("TextLabel { TextSize: 12 }", "TextLabel { TextSize: 20 }");
+
+            assert_eq!(inst.get_prop_value("TextSize"), Some(&Value::Number(12f64)));
+        }
+
+        #[test]
+        fn prefer_later_rules() {
+            let inst = styled("TextLabel {}", "TextLabel { TextSize: 20 } TextLabel { TextSize: 24 }");
+
+            assert_eq!(inst.get_prop_value("TextSize"), Some(&Value::Number(24f64)));
+            assert_eq!(inst.properties().iter().filter(|p| p.get_name() == "TextSize").count(), 1);
+        }
+
+        macro_rules! test_cascade {
+            ($test_name: ident, $rg: expr, $rgss: expr, $expected: expr) => {
+                #[test]
+                fn $test_name() {
+                    let inst = styled($rg, $rgss);
+
+                    assert_eq!(inst.children()[0].get_prop_value("TextSize"),
+                        Some(&Value::Number($expected as f64)));
+                }
+            }
+        }
+
+        test_cascade!(prefer_class_over_universal, "Frame { TextLabel \"Title\" {} }",
+            "TextLabel { TextSize: 1 } * { TextSize: 2 }", 1);
+        test_cascade!(prefer_name_over_class, "Frame { TextLabel \"Title\" {} }",
+            "\"Title\" { TextSize: 1 } TextLabel { TextSize: 2 }", 1);
+        test_cascade!(prefer_descendant_over_class, "Frame { TextLabel \"Title\" {} }",
+            "Frame TextLabel { TextSize: 1 } TextLabel { TextSize: 2 }", 1);
+        test_cascade!(prefer_important_over_specific, "Frame { TextLabel \"Title\" {} }",
+            "TextLabel { TextSize: 1 !important } \"Title\" { TextSize: 2 }", 1);
+        test_cascade!(prefer_important_over_inline, "Frame { TextLabel { TextSize: 2 } }",
+            "* { TextSize: 1 !important }", 1);
+        test_cascade!(prefer_important_inline, "Frame { TextLabel { TextSize: 2 !important } }",
+            "TextLabel { TextSize: 1 !important }", 2);
+        test_cascade!(prefer_later_important, "Frame { TextLabel {} }",
+            "TextLabel { TextSize: 1 !important } TextLabel { TextSize: 2 !important }", 2);
+
+        #[test]
+        fn record_origin() {
+            let inst = styled("TextLabel { Text: \"Hi\" }",
+                "* { TextSize: 1 } TextLabel { TextSize: 2; Visible: true }");
+
+            let origin_of = |name: &str| inst.properties().iter()
+                .find(|p| p.get_name() == name)
+                .map(|p| p.get_origin().clone())
+                .unwrap();
+
+            assert_eq!(origin_of("Text"), PropertyOrigin::Inline);
+            assert_eq!(origin_of("TextSize"), PropertyOrigin::Style("TextLabel".to_owned(), 1));
+            assert_eq!(origin_of("Visible"), PropertyOrigin::Style("TextLabel".to_owned(), 1));
+        }
     }
 
     mod code_fragments {
@@ -251,7 +508,7 @@ mod can_parse {
                     let parsed = atoms::file($contents);
 
                     if !parsed.is_ok() {
-                        panic!(format!("Expected success but got {:?}", parsed));
+                        panic!("Expected success but got {:?}", parsed);
                     }
                 }
             }
@@ -278,6 +535,275 @@ mod can_parse {
 
                                         Frame{}
                                     }"#);
-        
+        should_compile!(line_comments, "// Menu\nFrame { // the root\n    Visible: true // shown\n}\n//");
+        should_compile!(block_comments, "Frame /* root */ { TextColor3 /* x */ : RGB(0, 1, 2) /* y */ }");
+        should_compile!(four_slashes, "//// not a doc comment\nFrame { //// nor this\n}");
+
+        macro_rules! should_not_compile {
+            ($test_name: ident, $contents: expr) => {
+                #[test]
+                fn $test_name() {
+                    assert!(atoms::file($contents).is_err());
+                }
+            }
+        }
+
+        should_compile!(instance_ids, "Frame \"Menu\" @menu { TextLabel @title {} }");
+
+        should_not_compile!(unclosed_block_comment, "Frame {} /* ");
+        should_not_compile!(comment_inside_value, "Frame { TextColor3: RGB(/* r */ 0, 1, 2) }");
+        should_not_compile!(id_before_name, "Frame @menu \"Menu\" {}");
+        should_not_compile!(doc_comment_on_property, "Frame {\n    /// Hidden\n    Visible: false\n}");
+        should_not_compile!(trailing_doc_comment, "Frame {}\n/// Nothing");
+
+        #[test]
+        fn keep_doc_comments() {
+            let (nodes, _) = atoms::file("/// The menu\n///   indented\n///\nFrame {\n    /// A child\n    TextLabel {}\n}").unwrap();
+
+            match nodes[0] {
+                RgNode::Instance(ref inst) => {
+                    assert_eq!(inst.docs(), &vec!["The menu", "  indented", ""]);
+                    assert_eq!(inst.children()[0].docs(), &vec!["A child"]);
+                }
+                _ => panic!("Expected an instance"),
+            }
+        }
+
+        #[test]
+        fn stylesheet_comments() {
+            assert!(atoms::stylesheet("/// Labels\nTextLabel { /* big */ TextSize: 20 } // done").is_ok());
+        }
     }
-}
\ No newline at end of file
+
+    mod errors_that {
+        use super::super::{parse_str, parse_stylesheet};
+        use rg::diagnostics::{codes, Diagnostics};
+        use rg::styles::StyleSheet;
+        use codegen::test_util::test_api;
+
+        fn diagnostics_of(rg: &str, rgss: &str) -> Diagnostics {
+            let styles = parse_stylesheet(rgss).unwrap();
+
+            parse_str(rg, &styles, &test_api()).1
+        }
+
+        fn rendered(rg: &str, rgss: &str) -> Vec<String> {
+            diagnostics_of(rg, rgss).iter().map(|d| d.render("menu.rg", rg)).collect()
+        }
+
+        // The code between the brackets of `error[E0001]`
+        fn codes_of(rg: &str) -> Vec<String> {
+            rendered(rg, "").iter()
+                .map(|d| d.split(['[', ']']).nth(1).unwrap().to_owned())
+                .collect()
+        }
+
+        #[test]
+        fn point_at_property_name() {
+            assert_eq!(rendered("Frame {\n    Sise: 1\n}", ""), vec![
+                "menu.rg:2:5: error[E0004]: Class Frame with name Frame does not have property Sise\n  \
+                |\n2 |     Sise: 1\n  |     ^^^^\n  = help: did you mean `Size`?"]);
+        }
+
+        #[test]
+        fn suggest_class_names() {
+            assert!(rendered("TextLabl {}", "")[0].ends_with("= help: did you mean `TextLabel`?"));
+        }
+
+        #[test]
+        fn suggest_inherited_properties() {
+            assert!(rendered("TextLabel { BackgroundColor: #FFFFFF }", "")[0].ends_with("= help: did you mean `BackgroundColor3`?"));
+        }
+
+        #[test]
+        fn suggest_properties_taking_the_value() {
+            let errors = rendered("TextLabel { TextColor: #FFFFFF }", "");
+
+            assert_eq!(errors.len(), 1);
+            assert!(errors[0].contains("error[E0009]: Property TextColor is a BrickColor"), "{}", errors[0]);
+            assert!(errors[0].ends_with("= help: did you mean `TextColor3`?"), "{}", errors[0]);
+        }
+
+        #[test]
+        fn suggest_enum_members() {
+            assert!(rendered("TextLabel { TextXAlignment: Centre }", "")[0].ends_with("= help: did you mean `Center`?"));
+            assert!(rendered("TextLabel { TextXAlignment: Enum.TextXAlignment.Centre }", "")[0]
+                .ends_with("= help: did you mean `Enum.TextXAlignment.Center`?"));
+        }
+
+        #[test]
+        fn reject_strings_as_enum_members() {
+            let error = &rendered("TextLabel { TextXAlignment: \"Center\" }", "")[0];
+
+            assert!(error.contains("error[E0005]: Property TextXAlignment cannot be set with value \"Center\""), "{}", error);
+            assert!(error.ends_with("= note: TextXAlignment takes a member of Enum.TextXAlignment\n  \
+                = help: did you mean `Center`?"), "{}", error);
+        }
+
+        #[test]
+        fn reject_members_of_other_enums() {
+            assert!(codes_of("TextLabel { TextXAlignment: Enum.TextXAlignment.Center }").is_empty());
+
+            let error = &rendered("TextLabel { TextXAlignment: Enum.Font.Center }", "")[0];
+            assert!(error.contains("error[E0005]: Property TextXAlignment cannot be set with value Enum.Font.Center"),
+                "{}", error);
+            assert!(error.ends_with("= help: did you mean `Enum.TextXAlignment.Center`?"), "{}", error);
+        }
+
+        #[test]
+        fn resolve_enum_members() {
+            use rg::values::RgNode;
+
+            let styles = parse_stylesheet("TextLabel { TextXAlignment: Right }").unwrap();
+            let (nodes, _) = parse_str("Frame { TextLabel {} TextLabel { TextXAlignment: Enum.TextXAlignment.Center } }",
+                &styles, &test_api());
+
+            let resolved: Vec<_> = match nodes[0] {
+                RgNode::Instance(ref frame) => frame.children().iter()
+                    .map(|label| match label.get_prop_value("TextXAlignment") {
+                        Some(::rg::Value::EnumMember(ref member)) => member.resolved().map(|(e, v)| (e.to_owned(), v)),
+                        value => panic!("Expected a member but got {:?}", value),
+                    })
+                    .collect(),
+                _ => unreachable!(),
+            };
+
+            assert_eq!(resolved, vec![Some(("TextXAlignment".to_owned(), 1)), Some(("TextXAlignment".to_owned(), 2))]);
+        }
+
+        #[test]
+        fn suggest_nothing_when_nothing_is_close() {
+            assert!(!rendered("Frame { Wobble: 1 }", "")[0].contains("help"));
+        }
+
+        #[test]
+        fn point_at_value() {
+            let error = &rendered("Frame {\n    Visible: \"yes\"\n}", "")[0];
+
+            assert!(error.starts_with("menu.rg:2:14: error[E0005]: Property Visible cannot be set"), "{}", error);
+            assert!(error.ends_with("\n  |              ^^^^^"), "{}", error);
+        }
+
+        #[test]
+        fn point_at_styled_instance() {
+            let error = &rendered("Frame {\n    TextLabel {}\n}", "TextLabel { Visible: 1 }")[0];
+
+            assert!(error.starts_with("menu.rg:2:5: "), "{}", error);
+            assert!(error.ends_with("= note: set by style rule #1 `TextLabel`"), "{}", error);
+        }
+
+        #[test]
+        fn point_at_syntax_error() {
+            let (nodes, diagnostics) = parse_str("Frame {\n    Visible true\n}", &StyleSheet::default(), &test_api());
+            let error = diagnostics.iter().next().unwrap().render("menu.rg", "Frame {\n    Visible true\n}");
+
+            assert!(nodes.is_empty());
+            assert!(error.starts_with("menu.rg:2:13: error[E0001]: Expected one of"), "{}", error);
+        }
+
+        #[test]
+        fn report_every_error() {
+            assert_eq!(codes_of("Frame { Sise: 1 Visible: 2 Label {} TextLabel { Txt: \"\" } }"),
+                vec![codes::UNKNOWN_PROPERTY, codes::MISMATCHED_VALUE, codes::UNKNOWN_CLASS, codes::UNKNOWN_PROPERTY]);
+        }
+
+        #[test]
+        fn check_keypoint_times() {
+            assert!(codes_of("UIGradient { Transparency: NumberSequence(0: 1, 1: 0) }").is_empty());
+            assert_eq!(codes_of("UIGradient { Transparency: NumberSequence(0: 1, 1.5: 0) }"), vec![codes::INVALID_VALUE]);
+
+            let error = &rendered("UIGradient { Color: ColorSequence(0: #FFFFFF, 0.8: #000000, 0.2: #FFFFFF) }", "")[0];
+            assert!(error.contains("error[E0008]: Keypoint times must be in order, but 0.2 comes after 0.8"), "{}", error);
+        }
+
+        macro_rules! test_sequence_error {
+            ($test_name: ident, $value: expr, $message: expr) => {
+                #[test]
+                fn $test_name() {
+                    let error = &rendered(concat!("UIGradient { Transparency: ", $value, " }"), "")[0];
+
+                    assert!(error.contains(concat!("error[E0008]: ", $message)), "{}", error);
+                }
+            }
+        }
+
+        test_sequence_error!(single_keypoint, "NumberSequence(0.5: 1)", "A sequence needs at least two keypoints");
+        test_sequence_error!(first_keypoint_after_start, "NumberSequence(0.2: 1, 1: 0)",
+            "The first keypoint must be at time 0, not 0.2");
+        test_sequence_error!(last_keypoint_before_end, "NumberSequence(0: 1, 0.5: 0)",
+            "The last keypoint must be at time 1, not 0.5");
+
+        macro_rules! test_invalid_value {
+            ($test_name: ident, $rg: expr, $message: expr) => {
+                #[test]
+                fn $test_name() {
+                    let error = &rendered($rg, "")[0];
+
+                    assert!(error.contains(concat!("error[E0008]: ", $message)), "{}", error);
+                }
+            }
+        }
+
+        test_invalid_value!(fractional_integer, "Frame { ZIndex: 1.5 }", "The number 1.5 is not a whole number");
+        test_invalid_value!(integer_out_of_range, "Frame { ZIndex: 3000000000 }",
+            "The number 3000000000 does not fit in a 32 bit integer");
+        test_invalid_value!(int64_out_of_range, "ParticleEmitter { Seed: 10000000000000000000 }",
+            "The number 10000000000000000000 does not fit in a 64 bit integer");
+        test_invalid_value!(fractional_offset, "Frame { Size: UDim2(0, 10, 0, 2.5) }", "The offset 2.5 is not a whole number");
+        test_invalid_value!(fractional_udim_offset, "UICorner { CornerRadius: UDim(0.5, 0.25) }",
+            "The offset 0.25 is not a whole number");
+
+        #[test]
+        fn whole_numbers_in_range() {
+            assert!(codes_of("Frame { ZIndex: -2147483648 Size: UDim2(0.5, 10, 1, -4) }").is_empty());
+            assert!(codes_of("ParticleEmitter { Seed: 9007199254740992 }").is_empty());
+        }
+
+        #[test]
+        fn check_number_ranges() {
+            assert_eq!(codes_of("ParticleEmitter { Lifetime: NumberRange(5, 1) }"), vec![codes::INVALID_VALUE]);
+            assert_eq!(codes_of("UIGradient { Transparency: NumberRange(0, 1) }"), vec![codes::MISMATCHED_VALUE]);
+        }
+
+        #[test]
+        fn report_unsupported_types() {
+            assert_eq!(codes_of("Frame { BorderColor: #FFFFFF BorderColor: RGB(0, 0, 0) }"),
+                vec![codes::UNSUPPORTED_TYPE, codes::UNSUPPORTED_TYPE, codes::DUPLICATE_PROPERTY]);
+
+            let error = &rendered("Frame { BorderColor: #FFFFFF }", "")[0];
+            assert!(error.contains("error[E0009]: Property BorderColor is a BrickColor, which .rg has no values for"), "{}", error);
+        }
+
+        #[test]
+        fn resolve_references() {
+            assert!(codes_of("Frame { NextSelectionUp: @title TextLabel @title {} }").is_empty());
+            assert!(codes_of("Frame @menu { TextLabel { NextSelectionUp: @menu } }").is_empty());
+            assert_eq!(codes_of("Frame { NextSelectionUp: \"Menu\" }"), vec![codes::MISMATCHED_VALUE]);
+
+            let error = &rendered("Frame { NextSelectionUp: @titl TextLabel @title {} }", "")[0];
+            assert!(error.contains("error[E0011]: No instance has id @titl"), "{}", error);
+            assert!(error.ends_with("= help: did you mean `@title`?"), "{}", error);
+        }
+
+        #[test]
+        fn check_referenced_classes() {
+            let error = &rendered("Frame { NextSelectionUp: @round UICorner @round {} }", "")[0];
+
+            assert!(error.contains("error[E0005]: Property NextSelectionUp takes a GuiObject, but @round is a UICorner"),
+                "{}", error);
+        }
+
+        #[test]
+        fn reject_duplicate_ids() {
+            let error = &rendered("Frame @menu {}\nFrame @menu {}", "")[0];
+
+            assert!(error.starts_with("menu.rg:2:7: error[E0010]: Another instance already has id @menu"), "{}", error);
+        }
+
+        #[test]
+        fn warn_about_duplicates() {
+            assert!(!diagnostics_of("Frame { ZIndex: 1 ZIndex: 2 }", "").has_errors());
+            assert_eq!(codes_of("Frame { ZIndex: 1 ZIndex: 2 }"), vec![codes::DUPLICATE_PROPERTY]);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use rg::parser;
+use rg::values::{Comments, RgInstance, RgNode, Value};
+use rg::diagnostics::Diagnostic;
+
+const INDENT: &str = "    ";
+
+pub fn print_value(value: &Value) -> String {
+    match value {
+        Value::StringLiteral(ref s) => format!("\"{}\"", s),
+        Value::Number(n) => n.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Color(r, g, b) => format!("#{:02X}{:02X}{:02X}", r, g, b),
+        Value::UDim2(xs, xo, ys, yo) => format!("UDim2({}, {}, {}, {})", xs, xo, ys, yo),
+        Value::UDim(s, o) => format!("UDim({}, {})", s, o),
+        Value::Vector2(x, y) => format!("Vector2({}, {})", x, y),
+        Value::Vector3(x, y, z) => format!("Vector3({}, {}, {})", x, y, z),
+        Value::Rect(x0, y0, x1, y1) => format!("Rect({}, {}, {}, {})", x0, y0, x1, y1),
+        Value::NumberSequence(ref keypoints) => {
+            let keypoints: Vec<String> = keypoints.iter().map(|(time, n)| format!("{}: {}", time, n)).collect();
+
+            format!("NumberSequence({})", keypoints.join(", "))
+        }
+        Value::ColorSequence(ref keypoints) => {
+            let keypoints: Vec<String> = keypoints.iter()
+                .map(|(time, (r, g, b))| format!("{}: {}", time, print_value(&Value::Color(*r, *g, *b))))
+                .collect();
+
+            format!("ColorSequence({})", keypoints.join(", "))
+        }
+        Value::NumberRange(min, max) => format!("NumberRange({}, {})", min, max),
+        Value::EnumMember(ref member) => member.to_string(),
+        Value::Reference(ref id) => format!("@{}", id),
+    }
+}
+
+// The node's leading comments on lines of their own, returning the line it
+// is then written on with the comments trailing it.
+fn print_commented(line: String, comments: &Comments, indent: &str, out: &mut Vec<String>) -> String {
+    out.extend(comments.leading().iter().map(|comment| format!("{}{}", indent, comment)));
+
+    if comments.trailing().is_empty() {
+        line
+    } else {
+        format!("{} {}", line, comments.trailing().join(" "))
+    }
+}
+
+fn print_instance(inst: &RgInstance, depth: usize, sort_properties: bool, out: &mut Vec<String>) {
+    let indent = INDENT.repeat(depth);
+    let child_indent = INDENT.repeat(depth + 1);
+
+    let mut header = match inst.get_prop_value("Name") {
+        Some(Value::StringLiteral(ref name)) => format!("{}{} \"{}\"", indent, inst.get_class_name(), name),
+        _ => format!("{}{}", indent, inst.get_class_name()),
+    };
+
+    if let Some(id) = inst.id() {
+        header.push_str(&format!(" @{}", id));
+    }
+
+    out.extend(inst.comments().leading().iter().map(|comment| format!("{}{}", indent, comment)));
+
+    for doc in inst.docs() {
+        out.push(format!("{}///{}", indent, if doc.is_empty() { String::new() } else { format!(" {}", doc) }));
+    }
+
+    let mut props: Vec<_> = inst.properties().iter()
+        .filter(|prop| !prop.get_name().starts_with('_'))
+        .filter(|prop| !(prop.get_name() == "Name" && prop.get_value().get_type() == "String"))
+        .collect();
+
+    if sort_properties {
+        props.sort_by_key(|prop| prop.get_name());
+    }
+
+    let trailing = Comments::new(vec![], inst.comments().trailing().clone());
+
+    if props.is_empty() && inst.children().is_empty() && inst.closing_comments().is_empty() {
+        let line = print_commented(format!("{} {{}}", header), &trailing, &indent, out);
+        out.push(line);
+        return;
+    }
+
+    out.push(format!("{} {{", header));
+
+    for prop in &props {
+        let line = format!("{}{}: {}{}", child_indent, prop.get_name(), print_value(prop.get_value()),
+            if prop.is_important() { " !important" } else { "" });
+        let line = print_commented(line, prop.comments(), &child_indent, out);
+        out.push(line);
+    }
+
+    for (i, child) in inst.children().iter().enumerate() {
+        if i > 0 || !props.is_empty() {
+            out.push(String::new());
+        }
+
+        print_instance(child, depth + 1, sort_properties, out);
+    }
+
+    out.extend(inst.closing_comments().iter().map(|comment| format!("{}{}", child_indent, comment)));
+
+    let line = print_commented(format!("{}}}", indent), &trailing, &indent, out);
+    out.push(line);
+}
+
+fn print_instances(instances: &[RgInstance], sort_properties: bool, out: &mut Vec<String>) {
+    for (i, inst) in instances.iter().enumerate() {
+        if i > 0 {
+            out.push(String::new());
+        }
+
+        print_instance(inst, 0, sort_properties, out);
+    }
+}
+
+// Writes instances back out as .rg source, using the Name shorthand and
+// listing properties before children.
+pub fn print_file(instances: &[RgInstance], sort_properties: bool) -> String {
+    let mut out = vec![];
+    print_instances(instances, sort_properties, &mut out);
+
+    out.join("\n") + "\n"
+}
+
+// The source laid out the one way rgss fmt accepts. Comments stay next to
+// the instance or property they were written by, on their own lines unless
+// they followed it on its last line.
+pub fn format_str(contents: &str, sort_properties: bool) -> Result<String, Diagnostic> {
+    let (parsed, end_comments) = parser::parse_file(contents)?;
+
+    let instances: Vec<RgInstance> = parsed.into_iter()
+        .filter_map(|node| match node {
+            RgNode::Instance(inst) => Some(inst),
+            RgNode::Property(_) => None,
+        })
+        .collect();
+
+    let mut out = vec![];
+    print_instances(&instances, sort_properties, &mut out);
+
+    if !instances.is_empty() && !end_comments.is_empty() {
+        out.push(String::new());
+    }
+
+    out.extend(end_comments);
+    Ok(out.join("\n") + "\n")
+}
+
+#[cfg(test)]
+mod can_format {
+    use super::*;
+
+    macro_rules! test_format {
+        ($test_name: ident, $source: expr, $expected: expr) => {
+            #[test]
+            fn $test_name() {
+                let formatted = format_str($source, false).unwrap();
+
+                assert_eq!(formatted, $expected);
+                assert_eq!(format_str(&formatted, false).unwrap(), formatted);
+            }
+        }
+    }
+
+    test_format!(empty_file, "", "\n");
+    test_format!(indentation, "Frame{Visible:true;ZIndex:2}",
+        "Frame {\n    Visible: true\n    ZIndex: 2\n}\n");
+    test_format!(colours_as_hex, "Frame { BackgroundColor3: RGB (255 , 0,16) }",
+        "Frame {\n    BackgroundColor3: #FF0010\n}\n");
+    test_format!(children_after_properties, "Frame \"Menu\" { TextLabel {} Visible: false ImageLabel {} }",
+        "Frame \"Menu\" {\n    Visible: false\n\n    TextLabel {}\n\n    ImageLabel {}\n}\n");
+    test_format!(keep_escapes_and_importance, "TextLabel { Text: \"a\\\"b\" !important }",
+        "TextLabel {\n    Text: \"a\\\"b\" !important\n}\n");
+
+    test_format!(sequences, "UIGradient { Color: ColorSequence(0:RGB(255, 0, 0),1:#0000FF) Transparency: NumberSequence(0:0,1:0.5) }",
+        "UIGradient {\n    Color: ColorSequence(0: #FF0000, 1: #0000FF)\n    Transparency: NumberSequence(0: 0, 1: 0.5)\n}\n");
+
+    test_format!(ids_and_references, "Frame \"Menu\"@menu{NextSelectionUp:@menu}",
+        "Frame \"Menu\" @menu {\n    NextSelectionUp: @menu\n}\n");
+
+    test_format!(doc_comments, "///Menu\n///\nFrame { /// Title\n TextLabel {} }",
+        "/// Menu\n///\nFrame {\n    /// Title\n    TextLabel {}\n}\n");
+
+    test_format!(line_comments, "// The menu\nFrame{// shown\nVisible:true// for now\nTextLabel{}// the title\n// more later\n}\n// the end",
+        "// The menu\nFrame {\n    // shown\n    Visible: true // for now\n\n    TextLabel {} // the title\n    \
+        // more later\n}\n\n// the end\n");
+    test_format!(block_comments, "/* The\n   menu */ Frame /* main */ \"Menu\" { /* x */ } /* done */",
+        "/* The\n   menu */\n/* main */\nFrame \"Menu\" {\n    /* x */\n} /* done */\n");
+    test_format!(comments_and_docs, "// A\n/// Menu\nFrame { ZIndex: /* above */ 2 !important; /* z */ }",
+        "// A\n/// Menu\nFrame {\n    /* above */\n    ZIndex: 2 !important /* z */\n}\n");
+    test_format!(comments_around_values, "Frame { Size: /* full */ UDim2(1, 0, 1, 0) /* wide */ }",
+        "Frame {\n    /* full */\n    Size: UDim2(1, 0, 1, 0) /* wide */\n}\n");
+    test_format!(only_comments, "  // Nothing yet\n", "// Nothing yet\n");
+
+    #[test]
+    fn reject_comments_inside_values() {
+        assert!(format_str("Frame { Size: UDim2(1, 0, /* inner */ 1, 0) }", false).is_err());
+        assert!(format_str("UIGradient { Color: ColorSequence(0: #FF0000, // red\n1: #0000FF) }", false).is_err());
+        assert!(format_str("Frame { ZIndex: 2 ! /* x */ important }", false).is_err());
+    }
+
+    #[test]
+    fn keep_comment_text_in_strings() {
+        assert_eq!(format_str("Frame { Text: \"// not a comment\" }", false).unwrap(),
+            "Frame {\n    Text: \"// not a comment\"\n}\n");
+    }
+
+    #[test]
+    fn sorted_properties_keep_their_comments() {
+        assert_eq!(format_str("Frame {\n    // last\n    ZIndex: 1\n    Visible: true // first\n}", true).unwrap(),
+            "Frame {\n    Visible: true // first\n    // last\n    ZIndex: 1\n}\n");
+    }
+
+    #[test]
+    fn sort_properties() {
+        assert_eq!(format_str("Frame { ZIndex: 1 Visible: true }", true).unwrap(),
+            "Frame {\n    Visible: true\n    ZIndex: 1\n}\n");
+    }
+
+    #[test]
+    fn reject_syntax_errors() {
+        assert!(format_str("Frame {", false).is_err());
+    }
+}
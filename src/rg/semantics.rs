@@ -1,66 +1,304 @@
-use roblox::{RobloxApi, initalise as get_roblox_api};
-use rg::values::{RgInstance, RgProperty};
+use std::collections::HashMap;
+use roblox::RobloxApi;
+use roblox::api::{Instance, Property, PropertyType, Enum};
+use rg::values::{RgNode, RgInstance, RgProperty, PropertyOrigin, Value};
+use rg::diagnostics::{codes, Diagnostic, Diagnostics};
+use rg::printer::print_value;
+use rg::suggestions;
 
-pub struct SemanticsChecker {
-    api: RobloxApi
+pub struct SemanticsChecker<'a> {
+    api: &'a RobloxApi,
+    // The class of every instance in the file with an id
+    ids: HashMap<String, String>,
 }
 
-pub type SemanticsResult = Result<(), String>;
-
 fn get_string_val<'a>(inst: &'a RgInstance, val: &'a str) -> &'a str {
     inst.get_prop_value(val).unwrap().as_str()
 }
 
+// Instances without a Name are called after their class, as in Roblox.
+fn get_name(inst: &RgInstance) -> &str {
+    match inst.get_prop_value("Name") {
+        Some(Value::StringLiteral(ref name)) => name,
+        _ => inst.get_class_name(),
+    }
+}
+
+// Properties set by a style rule were written in a stylesheet, so they are
+// reported at the instance the rule matched, noting which rule it was.
+fn from_origin(inst: &RgInstance, prop: &RgProperty, diagnostic: Diagnostic) -> Diagnostic {
+    match prop.get_origin() {
+        PropertyOrigin::Inline => diagnostic,
+        origin => diagnostic.with_span(inst.class_span())
+            .with_note(format!("set by {}", origin))
+            .without_suggestion_spans(),
+    }
+}
+
+// Integers are written as numbers, which must be whole and fit in the bits
+// Roblox keeps them in rather than be rounded or clamped.
+fn invalid_integer(what: &str, n: f64, bits: i32) -> Option<String> {
+    let limit = 2f64.powi(bits - 1);
+
+    if n.fract() != 0.0 {
+        Some(format!("{} {} is not a whole number", what, n))
+    } else if !(-limit..limit).contains(&n) {
+        Some(format!("{} {} does not fit in a {} bit integer", what, n, bits))
+    } else {
+        None
+    }
+}
+
+// What Roblox would refuse to construct the value from, or couldn't hold,
+// which the grammar can't rule out.
+fn invalid_value(prop_type: &PropertyType, value: &Value) -> Option<String> {
+    match (prop_type, value) {
+        (PropertyType::Int, Value::Number(n)) => return invalid_integer("The number", *n, 32),
+        (PropertyType::Int64, Value::Number(n)) => return invalid_integer("The number", *n, 64),
+        (PropertyType::UDim, Value::UDim(_, offset)) => return invalid_integer("The offset", *offset, 32),
+        (PropertyType::UDim2, Value::UDim2(_, x_offset, _, y_offset)) => return invalid_integer("The offset", *x_offset, 32)
+            .or_else(|| invalid_integer("The offset", *y_offset, 32)),
+        _ => {}
+    }
+
+    let times: Vec<f64> = match value {
+        Value::NumberSequence(ref keypoints) => keypoints.iter().map(|(time, _)| *time).collect(),
+        Value::ColorSequence(ref keypoints) => keypoints.iter().map(|(time, _)| *time).collect(),
+        Value::NumberRange(min, max) if min > max =>
+            return Some(format!("NumberRange minimum {} is greater than its maximum {}", min, max)),
+        _ => return None,
+    };
+
+    if times.len() < 2 {
+        return Some("A sequence needs at least two keypoints".to_owned());
+    }
+
+    if let Some(time) = times.iter().find(|time| !(0.0..=1.0).contains(*time)) {
+        return Some(format!("Keypoint time {} is not between 0 and 1", time));
+    }
+
+    if let Some(pair) = times.windows(2).find(|pair| pair[0] > pair[1]) {
+        return Some(format!("Keypoint times must be in order, but {} comes after {}", pair[1], pair[0]));
+    }
+
+    match (times[0], times[times.len() - 1]) {
+        (first, _) if first != 0.0 => Some(format!("The first keypoint must be at time 0, not {}", first)),
+        (_, last) if last != 1.0 => Some(format!("The last keypoint must be at time 1, not {}", last)),
+        _ => None,
+    }
+}
+
+// Properties of these types exist but no .rg value can be given to them.
+fn unsupported_type(roblox_prop: &Property) -> Option<String> {
+    match roblox_prop.get_type() {
+        PropertyType::DataType(ref data_type) => Some(format!(
+            "Property {} is a {}, which .rg has no values for", roblox_prop.get_name(), data_type)),
+        _ => None,
+    }
+}
+
+fn collect_instance_ids(inst: &RgInstance, ids: &mut HashMap<String, String>, diagnostics: &mut Diagnostics) {
+    if let (Some(id), Some(span)) = (inst.id(), inst.id_span()) {
+        if ids.contains_key(id) {
+            diagnostics.push(Diagnostic::error(codes::DUPLICATE_ID,
+                format!("Another instance already has id @{}", id), span));
+        } else {
+            ids.insert(id.to_owned(), inst.get_class_name().to_owned());
+        }
+    }
+
+    for child in inst.children() {
+        collect_instance_ids(child, ids, diagnostics);
+    }
+}
+
+// The ids given to instances anywhere in the file, which references can
+// point at from anywhere else in it.
+pub fn collect_ids(nodes: &[RgNode], diagnostics: &mut Diagnostics) -> HashMap<String, String> {
+    let mut ids = HashMap::new();
+
+    for node in nodes {
+        if let RgNode::Instance(ref inst) = node {
+            collect_instance_ids(inst, &mut ids, diagnostics);
+        }
+    }
+
+    ids
+}
+
+// Records the diagnostic and stops checking the node when the condition fails.
 macro_rules! check {
-    ($cond: expr, $fmt:expr, $($arg:tt)*) => {
+    ($diagnostics: expr, $cond: expr, $diagnostic: expr) => {
         if !($cond) {
-            return Err(format!(concat!($fmt, "\n"), $($arg)*));
+            $diagnostics.push($diagnostic);
+            return false;
         }
     }
 }
 
-impl SemanticsChecker {
-    pub fn new() -> Result<SemanticsChecker, &'static str> {
-        let api = get_roblox_api()?;
+impl<'a> SemanticsChecker<'a> {
+    pub fn new(api: &'a RobloxApi) -> SemanticsChecker<'a> {
+        SemanticsChecker { api, ids: HashMap::new() }
+    }
 
-        Ok(SemanticsChecker { api })
+    pub fn with_ids(self, ids: HashMap<String, String>) -> SemanticsChecker<'a> {
+        SemanticsChecker { ids, ..self }
     }
 
-    pub fn check_instance(&self, inst: &RgInstance) -> SemanticsResult {
-        self.check_instance_semantics(inst)?;
+    // The instance referred to must exist and be of the class the property takes.
+    fn check_reference(&self, roblox_prop: &Property, prop: &RgProperty) -> Option<Diagnostic> {
+        let (id, class) = match (prop.get_value(), roblox_prop.get_type()) {
+            (Value::Reference(ref id), PropertyType::Instance(ref class)) => (id, class),
+            _ => return None,
+        };
 
-        for prop in inst.properties() {
-            self.check_property_semantics(inst, prop)?;
+        match self.ids.get(id) {
+            None => Some(Diagnostic::error(codes::UNKNOWN_REFERENCE,
+                format!("No instance has id @{}", id), prop.value_span())
+                .with_suggestions(prop.value_span(), suggestions::closest(id, self.ids.keys().map(|id| id.as_str()))
+                    .into_iter().map(|id| format!("@{}", id)).collect())),
+            Some(target) if !self.api.inherits(target, class) => Some(Diagnostic::error(codes::MISMATCHED_VALUE,
+                format!("Property {} takes a {}, but @{} is a {}", roblox_prop.get_name(), class, id, target),
+                prop.value_span())),
+            Some(_) => None,
+        }
+    }
+
+    // Checks the whole tree, recording every problem rather than stopping at the first.
+    pub fn check_instance(&self, inst: &RgInstance, diagnostics: &mut Diagnostics) {
+        if self.check_instance_semantics(inst, diagnostics) {
+            for (i, prop) in inst.properties().iter().enumerate() {
+                self.check_property_semantics(inst, prop, diagnostics);
+
+                if inst.properties()[..i].iter().any(|p| p.get_name() == prop.get_name()) {
+                    diagnostics.push(Diagnostic::warning(codes::DUPLICATE_PROPERTY,
+                        format!("Property {} is set more than once", prop.get_name()),
+                        prop.name_span()));
+                }
+            }
         }
 
         for child in inst.children() {
-            self.check_instance(child)?;
+            self.check_instance(child, diagnostics);
+        }
+    }
+
+    fn class_suggestions(&self, class_name: &str) -> Vec<&str> {
+        let creatable = self.api.get_instances().iter()
+            .filter(|inst| !inst.has_tag("notCreatable"))
+            .map(|inst| inst.get_name());
+
+        suggestions::closest(class_name, creatable)
+    }
+
+    fn property_suggestions(&self, roblox_instance: &'a Instance, prop_name: &str) -> Vec<&str> {
+        let visible = self.api.get_properties(roblox_instance).into_iter()
+            .filter(|prop| !prop.has_tag("hidden"))
+            .map(|prop| prop.get_name());
+
+        suggestions::closest(prop_name, visible)
+    }
+
+    // Properties of the instance close to one that is deprecated or can't take
+    // the value, such as TextColor3 for TextColor, which do take it.
+    fn replacement_suggestions(&self, roblox_instance: &'a Instance, roblox_prop: &Property, value: &Value) -> Vec<&str> {
+        let replacements = self.api.get_properties(roblox_instance).into_iter()
+            .filter(|prop| prop.get_name() != roblox_prop.get_name())
+            .filter(|prop| !prop.has_tag("hidden") && !prop.has_tag("deprecated"))
+            .filter(|prop| unsupported_type(prop).is_none() && prop.can_take_value(self.api, value))
+            .map(|prop| prop.get_name());
+
+        suggestions::closest(roblox_prop.get_name(), replacements)
+    }
+
+    // Records the enum and value of every member assigned to an enum property,
+    // for code generators to use. Members which aren't in the property's enum
+    // are left as they are for check_instance to report.
+    pub fn resolve_enums(&self, inst: &mut RgInstance) {
+        if let Some(roblox_instance) = self.api.get_instance(inst.get_class_name()) {
+            for prop in inst.properties_mut() {
+                let enum_name = match self.api.get_property(roblox_instance, prop.get_name()).map(|p| p.get_type()) {
+                    Some(PropertyType::Enum(ref enum_name)) => enum_name,
+                    _ => continue,
+                };
+
+                let resolved = match prop.get_value() {
+                    Value::EnumMember(ref member) => self.api.get_enum(enum_name)
+                        .and_then(|roblox_enum| roblox_enum.resolve(member))
+                        .map(|value| member.clone().with_resolution(enum_name.clone(), value)),
+                    _ => None,
+                };
+
+                if let Some(member) = resolved {
+                    prop.set_value(Value::EnumMember(member));
+                }
+            }
+        }
+
+        for child in inst.children_mut() {
+            self.resolve_enums(child);
         }
+    }
+
+    // Members of the property's enum close to the value, qualified if the value was.
+    // Strings are never members, so they get the bare member instead.
+    fn member_suggestions(&self, roblox_enum: &Enum, value: &Value) -> Vec<String> {
+        let (written, qualified) = match value {
+            Value::EnumMember(ref member) => (member.name(), member.qualifier().is_some()),
+            Value::StringLiteral(ref text) => (text.as_str(), false),
+            _ => return vec![],
+        };
+
+        suggestions::closest(written, roblox_enum.get_member_names()).into_iter()
+            .map(|m| if qualified { format!("Enum.{}.{}", roblox_enum.get_name(), m) } else { m.to_owned() })
+            .collect()
+    }
 
-        Ok(())
+    fn mismatch(&self, roblox_instance: &'a Instance, roblox_prop: &Property, prop: &RgProperty) -> Diagnostic {
+        let diagnostic = Diagnostic::error(codes::MISMATCHED_VALUE,
+            format!("Property {} cannot be set with value {}", roblox_prop.get_name(), print_value(prop.get_value())),
+            prop.value_span())
+            .with_suggestions(prop.name_span(), self.replacement_suggestions(roblox_instance, roblox_prop, prop.get_value()));
+
+        let roblox_enum = match roblox_prop.get_type() {
+            PropertyType::Enum(ref enum_name) => self.api.get_enum(enum_name),
+            _ => None,
+        };
+
+        match roblox_enum {
+            Some(roblox_enum) => diagnostic
+                .with_note(format!("{} takes a member of Enum.{}", roblox_prop.get_name(), roblox_enum.get_name()))
+                .with_suggestions(prop.value_span(), self.member_suggestions(roblox_enum, prop.get_value())),
+            None => diagnostic,
+        }
     }
 
-    fn check_instance_semantics(&self, inst: &RgInstance) -> SemanticsResult {
+    // False when the class doesn't exist, as its properties can't be checked.
+    fn check_instance_semantics(&self, inst: &RgInstance, diagnostics: &mut Diagnostics) -> bool {
         let class_name = get_string_val(inst, "_ClassName");
 
         let instance = self.api.get_instance(class_name);
 
-        check!(instance.is_some(),
-            "Class {} does not exist",
-            class_name);
+        check!(diagnostics, instance.is_some(),
+            Diagnostic::error(codes::UNKNOWN_CLASS,
+                format!("Class {} does not exist", class_name),
+                inst.class_span())
+                .with_suggestions(inst.class_span(), self.class_suggestions(class_name)));
 
         let roblox_instance = instance.unwrap();
 
-        check!(!roblox_instance.has_tag("notCreatable"),
-            "Instance {} cannot be created",
-            roblox_instance.get_name());
+        if roblox_instance.has_tag("notCreatable") {
+            diagnostics.push(Diagnostic::error(codes::NOT_CREATABLE,
+                format!("Instance {} cannot be created", roblox_instance.get_name()),
+                inst.class_span()));
+        }
 
-        Ok(())
+        true
     }
 
-    fn check_property_semantics(&self, inst: &RgInstance, prop: &RgProperty) -> SemanticsResult {
+    fn check_property_semantics(&self, inst: &RgInstance, prop: &RgProperty, diagnostics: &mut Diagnostics) -> bool {
         if prop.get_name() == "_ClassName" {
-            return Ok(());
+            return true;
         }
 
         let roblox_instance = self.api.get_instance(
@@ -69,22 +307,45 @@ impl SemanticsChecker {
         let roblox_prop_opt = self.api.get_property(
             roblox_instance, prop.get_name());
 
-        check!(roblox_prop_opt.is_some(),
-            "Class {} with name {} does not have property {}",
-            get_string_val(inst, "_ClassName"),
-            get_string_val(inst, "Name"), 
-            prop.get_name());
+        check!(diagnostics, roblox_prop_opt.is_some(),
+            from_origin(inst, prop, Diagnostic::error(codes::UNKNOWN_PROPERTY,
+                format!("Class {} with name {} does not have property {}",
+                    get_string_val(inst, "_ClassName"), get_name(inst), prop.get_name()),
+                prop.name_span())
+                .with_suggestions(prop.name_span(), self.property_suggestions(roblox_instance, prop.get_name()))));
 
         let roblox_prop = roblox_prop_opt.unwrap();
-        check!(roblox_prop.can_take_value(&self.api, prop.get_value()),
-            "Property {} cannot be set with value {:?}",
-            roblox_prop.get_name(),
-            prop.get_value());
 
-        check!(!roblox_prop.has_tag("readonly"),
-            "The property {} is readonly",
-            roblox_prop.get_name());
+        let unsupported = unsupported_type(roblox_prop);
+
+        check!(diagnostics, unsupported.is_none(),
+            from_origin(inst, prop, Diagnostic::error(codes::UNSUPPORTED_TYPE, unsupported.unwrap(), prop.value_span())
+                .with_suggestions(prop.name_span(), self.replacement_suggestions(roblox_instance, roblox_prop, prop.get_value()))));
 
-        Ok(())
+        check!(diagnostics, roblox_prop.can_take_value(self.api, prop.get_value()),
+            from_origin(inst, prop, self.mismatch(roblox_instance, roblox_prop, prop)));
+
+        let reference_problem = self.check_reference(roblox_prop, prop);
+
+        check!(diagnostics, reference_problem.is_none(), from_origin(inst, prop, reference_problem.unwrap()));
+
+        let invalid = invalid_value(roblox_prop.get_type(), prop.get_value());
+
+        check!(diagnostics, invalid.is_none(),
+            from_origin(inst, prop, Diagnostic::error(codes::INVALID_VALUE, invalid.unwrap(), prop.value_span())));
+
+        check!(diagnostics, !roblox_prop.has_tag("readonly"),
+            from_origin(inst, prop, Diagnostic::error(codes::READONLY_PROPERTY,
+                format!("The property {} is readonly", roblox_prop.get_name()),
+                prop.name_span())));
+
+        if roblox_prop.has_tag("deprecated") {
+            diagnostics.push(from_origin(inst, prop, Diagnostic::warning(codes::DEPRECATED_PROPERTY,
+                format!("The property {} is deprecated", roblox_prop.get_name()),
+                prop.name_span())
+                .with_suggestions(prop.name_span(), self.replacement_suggestions(roblox_instance, roblox_prop, prop.get_value()))));
+        }
+
+        true
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,102 @@
+// Byte offsets into the source a node was parsed from. Nodes built by hand
+// rather than parsed have an empty span at the start of the file.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default, Serialize)]
+pub struct Span {
+    start: usize,
+    end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    // Inclusive of the end, so a cursor just after a name is still on it.
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset <= self.end
+    }
+}
+
+// The 1-based line and column of a byte offset, counting columns in characters.
+pub fn line_col(text: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(text.len());
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+    (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+}
+
+// Formats the message as `name:line:col: message` followed by the line the
+// span starts on, with the span underlined. Spans over several lines are
+// underlined to the end of their first line.
+pub fn render(name: &str, text: &str, span: Span, message: &str) -> String {
+    let start = span.start.min(text.len());
+    let (line, col) = line_col(text, start);
+
+    let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+    let end = span.end.max(start).min(line_end);
+
+    // Tabs are kept so the carets line up however the terminal renders them
+    let padding: String = text[line_start..start].chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let carets = "^".repeat(text[start..end].chars().count().max(1));
+    let gutter = " ".repeat(line.to_string().len());
+
+    format!("{}:{}:{}: {}\n{} |\n{} | {}\n{} | {}{}",
+        name, line, col, message,
+        gutter,
+        line, &text[line_start..line_end],
+        gutter, padding, carets)
+}
+
+#[cfg(test)]
+mod can_locate {
+    use super::*;
+
+    #[test]
+    fn first_line() {
+        assert_eq!(line_col("Frame {}", 6), (1, 7));
+    }
+
+    #[test]
+    fn later_line() {
+        assert_eq!(line_col("Frame {\n    Size: 1\n}", 12), (2, 5));
+    }
+
+    #[test]
+    fn count_characters() {
+        assert_eq!(line_col("Text: \"é\" X", 11), (1, 11));
+    }
+
+    #[test]
+    fn underline_span() {
+        let text = "Frame {\n    Sise: 1\n}";
+
+        assert_eq!(render("menu.rg", text, Span::new(12, 16), "Unknown property Sise"),
+            "menu.rg:2:5: Unknown property Sise\n  |\n2 |     Sise: 1\n  |     ^^^^");
+    }
+
+    #[test]
+    fn underline_first_line_only() {
+        let text = "Frame {\n}";
+
+        assert_eq!(render("menu.rg", text, Span::new(0, 9), "Empty"),
+            "menu.rg:1:1: Empty\n  |\n1 | Frame {\n  | ^^^^^^^");
+    }
+
+    #[test]
+    fn empty_span() {
+        assert_eq!(render("menu.rg", "Frame", Span::new(5, 5), "Expected {"),
+            "menu.rg:1:6: Expected {\n  |\n1 | Frame\n  |      ^");
+    }
+}
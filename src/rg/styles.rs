@@ -0,0 +1,239 @@
+use std::fmt;
+use rg::values::{RgInstance, RgProperty, PropertyOrigin, Value};
+use rg::span::Span;
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct RgSimpleSelector {
+    class_name: Option<String>,
+    name: Option<String>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct RgSelector {
+    parts: Vec<RgSimpleSelector>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RgStyleRule {
+    selector: RgSelector,
+    properties: Vec<RgProperty>,
+    // From the selector to the closing brace
+    span: Span,
+}
+
+#[derive(PartialEq, Debug, Default)]
+pub struct StyleSheet {
+    rules: Vec<RgStyleRule>,
+}
+
+// How many name and class selectors a selector is made of, name selectors
+// being the more specific. Compared lexicographically, so a universal
+// selector is the least specific of all.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy, Default)]
+pub struct Specificity {
+    names: usize,
+    classes: usize,
+}
+
+// Decides which of the declarations of a property wins. Later fields only
+// matter when all earlier ones are equal, the position in the cascade
+// being the final tie-breaker.
+type CascadeKey = (bool, bool, Specificity, usize);
+
+// What a selector gets to see of an instance, kept for each ancestor while
+// walking down the tree.
+struct Subject {
+    class_name: String,
+    name: Option<String>,
+}
+
+impl Subject {
+    fn of(inst: &RgInstance) -> Subject {
+        let class_name = inst.get_class_name().to_owned();
+        let name = match inst.get_prop_value("Name") {
+            Some(Value::StringLiteral(ref name)) => Some(name.clone()),
+            _ => None,
+        };
+
+        Subject { class_name, name }
+    }
+}
+
+impl RgSimpleSelector {
+    pub fn new(class_name: Option<String>, name: Option<String>) -> RgSimpleSelector {
+        RgSimpleSelector { class_name, name }
+    }
+
+    fn matches(&self, subject: &Subject) -> bool {
+        let class_matches = self.class_name.iter().all(|class| *class == subject.class_name);
+        let name_matches = self.name.iter().all(|name| subject.name.as_ref() == Some(name));
+
+        class_matches && name_matches
+    }
+}
+
+impl RgSelector {
+    pub fn new(parts: Vec<RgSimpleSelector>) -> RgSelector {
+        RgSelector { parts }
+    }
+
+    pub fn specificity(&self) -> Specificity {
+        let count = |f: fn(&RgSimpleSelector) -> bool| self.parts.iter().filter(|p| f(p)).count();
+
+        Specificity {
+            names: count(|part| part.name.is_some()),
+            classes: count(|part| part.class_name.is_some()),
+        }
+    }
+
+    // The last part must match the instance itself, every earlier part must
+    // match some ancestor, innermost first.
+    fn matches(&self, subject: &Subject, ancestors: &[Subject]) -> bool {
+        let (last, rest) = match self.parts.split_last() {
+            Some(split) => split,
+            None => return false,
+        };
+
+        if !last.matches(subject) {
+            return false;
+        }
+
+        let mut remaining = ancestors;
+        for part in rest.iter().rev() {
+            match remaining.iter().rposition(|ancestor| part.matches(ancestor)) {
+                Some(i) => remaining = &remaining[..i],
+                None => return false,
+            }
+        }
+
+        true
+    }
+}
+
+// As with instances, where a rule was written is left out of comparisons.
+impl PartialEq for RgStyleRule {
+    fn eq(&self, other: &RgStyleRule) -> bool {
+        self.selector == other.selector && self.properties == other.properties
+    }
+}
+
+impl RgStyleRule {
+    pub fn new(selector: RgSelector, properties: Vec<RgProperty>) -> RgStyleRule {
+        RgStyleRule { selector, properties, span: Span::default() }
+    }
+
+    pub fn with_span(self, span: Span) -> RgStyleRule {
+        RgStyleRule { span, ..self }
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    #[cfg(test)]
+    pub fn selector(&self) -> &RgSelector {
+        &self.selector
+    }
+
+    #[cfg(test)]
+    pub fn properties(&self) -> &Vec<RgProperty> {
+        &self.properties
+    }
+}
+
+impl StyleSheet {
+    pub fn new(rules: Vec<RgStyleRule>) -> StyleSheet {
+        StyleSheet { rules }
+    }
+
+    // Appends another sheet's rules, which then win ties against this sheet's.
+    pub fn extend(&mut self, other: StyleSheet) {
+        self.rules.extend(other.rules);
+    }
+
+    // The rules matching the last instance of the path, which runs from the
+    // outermost ancestor down.
+    pub fn matching_rules(&self, path: &[&RgInstance]) -> Vec<&RgStyleRule> {
+        let (inst, ancestors) = match path.split_last() {
+            Some(split) => split,
+            None => return vec![],
+        };
+
+        let subject = Subject::of(inst);
+        let ancestors: Vec<Subject> = ancestors.iter().map(|ancestor| Subject::of(ancestor)).collect();
+
+        self.rules.iter()
+            .filter(|rule| rule.selector.matches(&subject, &ancestors))
+            .collect()
+    }
+
+    // Merges every matching rule into the instance and its descendants.
+    // An !important declaration beats a normal one, then inline properties
+    // beat rules, then more specific rules beat less specific ones and finally
+    // later rules beat earlier ones.
+    pub fn apply(&self, inst: &mut RgInstance) {
+        self.apply_with_ancestors(inst, &mut vec![]);
+    }
+
+    fn apply_with_ancestors(&self, inst: &mut RgInstance, ancestors: &mut Vec<Subject>) {
+        let subject = Subject::of(inst);
+        let mut resolved: Vec<(CascadeKey, RgProperty)> = vec![];
+
+        let matching_rules = self.rules.iter().enumerate()
+            .filter(|(_, rule)| rule.selector.matches(&subject, ancestors));
+
+        for (position, rule) in matching_rules {
+            for prop in &rule.properties {
+                let key = (prop.is_important(), false, rule.selector.specificity(), position);
+
+                let beats_others = match resolved.iter().find(|(_, p)| p.get_name() == prop.get_name()) {
+                    Some((other_key, _)) => key > *other_key,
+                    None => true,
+                };
+
+                if beats_others {
+                    let origin = PropertyOrigin::Style(rule.selector.to_string(), position);
+
+                    resolved.retain(|(_, p)| p.get_name() != prop.get_name());
+                    resolved.push((key, prop.clone().with_origin(origin)));
+                }
+            }
+        }
+
+        for (key, prop) in resolved {
+            let beats_inline = match inst.properties().iter().find(|p| p.get_name() == prop.get_name()) {
+                Some(p) => key > (p.is_important(), true, Specificity::default(), 0),
+                None => true,
+            };
+
+            if beats_inline {
+                inst.set_property(prop);
+            }
+        }
+
+        ancestors.push(subject);
+        for child in inst.children_mut() {
+            self.apply_with_ancestors(child, ancestors);
+        }
+        ancestors.pop();
+    }
+}
+
+impl fmt::Display for RgSimpleSelector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.class_name, &self.name) {
+            (Some(class_name), Some(name)) => write!(f, "{} \"{}\"", class_name, name),
+            (Some(class_name), None) => write!(f, "{}", class_name),
+            (None, Some(name)) => write!(f, "\"{}\"", name),
+            (None, None) => write!(f, "*"),
+        }
+    }
+}
+
+impl fmt::Display for RgSelector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts: Vec<String> = self.parts.iter().map(|p| p.to_string()).collect();
+
+        write!(f, "{}", parts.join(" "))
+    }
+}
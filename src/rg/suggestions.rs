@@ -0,0 +1,62 @@
+use std::cmp;
+
+const MAX_SUGGESTIONS: usize = 3;
+
+// Levenshtein distance, ignoring case since `textcolor3` is as likely a typo as `TextColor`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == cb { 0 } else { 1 };
+            current.push(cmp::min(substitution, cmp::min(previous[j + 1], current[j]) + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+// The candidates close enough to the name to be what was meant, closest first.
+pub fn closest<'a, I>(name: &str, candidates: I) -> Vec<&'a str>
+    where I: IntoIterator<Item = &'a str>
+{
+    let max_distance = cmp::max(1, name.chars().count() / 3);
+
+    let mut close: Vec<(usize, &str)> = candidates.into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+
+    close.sort();
+    close.dedup();
+    close.into_iter().take(MAX_SUGGESTIONS).map(|(_, candidate)| candidate).collect()
+}
+
+#[cfg(test)]
+mod can_suggest {
+    use super::*;
+
+    macro_rules! test_closest {
+        ($test_name: ident, $name: expr, $candidates: expr, $expected: expr) => {
+            #[test]
+            fn $test_name() {
+                let expected: Vec<&str> = $expected;
+                assert_eq!(closest($name, $candidates.iter().cloned()), expected);
+            }
+        }
+    }
+
+    test_closest!(missing_character, "TextColor", ["TextColor3", "TextSize", "BackgroundColor3"], vec!["TextColor3"]);
+    test_closest!(swapped_characters, "Centre", ["Left", "Center", "Right"], vec!["Center"]);
+    test_closest!(different_case, "textlabel", ["TextLabel", "TextButton"], vec!["TextLabel"]);
+    test_closest!(closest_first, "Tex", ["Text", "Texts", "Tex2"], vec!["Tex2", "Text"]);
+    test_closest!(nothing_close, "Size", ["Visible", "ZIndex"], vec![]);
+    test_closest!(at_most_three, "Fram", ["Frame", "Frame", "Fra", "Frames", "Fxam"], vec!["Fra", "Frame", "Fxam"]);
+}
@@ -1,3 +1,6 @@
+use std::fmt;
+use rg::span::Span;
+
 
 #[derive(PartialEq, Debug, Serialize)]
 pub enum RgNode {
@@ -5,25 +8,125 @@ pub enum RgNode {
     Instance(RgInstance),
 }
 
-#[derive(PartialEq, Debug, Serialize)]
+#[derive(PartialEq, Debug, Serialize, Clone)]
+pub enum PropertyOrigin {
+    Inline,
+    // Selector of the rule that set the property and the rule's position in the cascade.
+    Style(String, usize),
+}
+
+#[derive(Debug, Serialize, Clone)]
 pub struct RgProperty {
     name: String,
     value: Value,
+    important: bool,
+    origin: PropertyOrigin,
+    name_span: Span,
+    value_span: Span,
+    // Comments other than doc comments, kept for rgss fmt
+    comments: Comments,
 }
 
-#[derive(PartialEq, Debug, Serialize)]
+#[derive(Debug, Serialize)]
 pub struct RgInstance {
     _properties: Vec<RgProperty>,
     _children: Vec<RgInstance>,
+    // From the class name to the closing brace
+    span: Span,
+    class_span: Span,
+    // The lines of the /// comments written before the instance
+    docs: Vec<String>,
+    // What references to the instance call it, along with where it was given
+    id: Option<(String, Span)>,
+    comments: Comments,
+    // The comments after the instance's last child, before its closing brace
+    closing_comments: Vec<String>,
+}
+
+// The comments written on the lines before a node, and after it on its last
+// line, as they were written including the // or /* */.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct Comments {
+    leading: Vec<String>,
+    trailing: Vec<String>,
+}
+
+impl Comments {
+    pub fn new(leading: Vec<String>, trailing: Vec<String>) -> Comments {
+        Comments { leading, trailing }
+    }
+
+    pub fn leading(&self) -> &Vec<String> {
+        &self.leading
+    }
+
+    pub fn trailing(&self) -> &Vec<String> {
+        &self.trailing
+    }
+}
+
+// Where a node was written doesn't change what it means, so spans and plain
+// comments are left out of comparisons.
+impl PartialEq for RgProperty {
+    fn eq(&self, other: &RgProperty) -> bool {
+        self.name == other.name && self.value == other.value
+            && self.important == other.important && self.origin == other.origin
+    }
+}
+
+impl PartialEq for RgInstance {
+    fn eq(&self, other: &RgInstance) -> bool {
+        self._properties == other._properties && self._children == other._children && self.docs == other.docs
+            && self.id() == other.id()
+    }
 }
 
 impl RgProperty {
     pub fn new(name: String, value: Value) -> RgProperty {
-        RgProperty { name, value }
+        RgProperty {
+            name, value, important: false, origin: PropertyOrigin::Inline,
+            name_span: Span::default(), value_span: Span::default(), comments: Comments::default(),
+        }
+    }
+
+    pub fn with_comments(self, comments: Comments) -> RgProperty {
+        RgProperty { comments, ..self }
+    }
+
+    pub fn comments(&self) -> &Comments {
+        &self.comments
+    }
+
+    pub fn with_spans(self, name_span: Span, value_span: Span) -> RgProperty {
+        RgProperty { name_span, value_span, ..self }
+    }
+
+    pub fn name_span(&self) -> Span {
+        self.name_span
+    }
+
+    pub fn value_span(&self) -> Span {
+        self.value_span
     }
 
     pub fn new_string(name: String, value: String) -> RgProperty {
-        RgProperty {name, value: Value::StringLiteral(value)}
+        RgProperty::new(name, Value::StringLiteral(value))
+    }
+
+    pub fn important(self) -> RgProperty {
+        RgProperty { important: true, ..self }
+    }
+
+    pub fn with_origin(self, origin: PropertyOrigin) -> RgProperty {
+        RgProperty { origin, ..self }
+    }
+
+    pub fn is_important(&self) -> bool {
+        self.important
+    }
+
+    pub fn get_origin(&self) -> &PropertyOrigin {
+        &self.origin
     }
 
     pub fn get_name(&self) -> &str  {
@@ -33,21 +136,92 @@ impl RgProperty {
     pub fn get_value(&self) -> &Value {
         &self.value
     }
+
+    pub fn set_value(&mut self, value: Value) {
+        self.value = value;
+    }
 }
 
 impl RgInstance {
     pub fn new(_properties: Vec<RgProperty>, _children: Vec<RgInstance>) -> RgInstance {
-        RgInstance { _properties, _children }
+        RgInstance {
+            _properties, _children, span: Span::default(), class_span: Span::default(), docs: vec![], id: None,
+            comments: Comments::default(), closing_comments: vec![],
+        }
+    }
+
+    pub fn with_comments(self, comments: Comments, closing_comments: Vec<String>) -> RgInstance {
+        RgInstance { comments, closing_comments, ..self }
+    }
+
+    pub fn comments(&self) -> &Comments {
+        &self.comments
+    }
+
+    pub fn closing_comments(&self) -> &Vec<String> {
+        &self.closing_comments
+    }
+
+    pub fn with_spans(self, span: Span, class_span: Span) -> RgInstance {
+        RgInstance { span, class_span, ..self }
+    }
+
+    pub fn with_docs(self, docs: Vec<String>) -> RgInstance {
+        RgInstance { docs, ..self }
+    }
+
+    pub fn docs(&self) -> &Vec<String> {
+        &self.docs
+    }
+
+    pub fn with_id(self, id: String, span: Span) -> RgInstance {
+        RgInstance { id: Some((id, span)), ..self }
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_ref().map(|(id, _)| id.as_str())
+    }
+
+    pub fn id_span(&self) -> Option<Span> {
+        self.id.as_ref().map(|(_, span)| *span)
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn class_span(&self) -> Span {
+        self.class_span
     }
 
     pub fn children(&self) -> &Vec<RgInstance> {
         &self._children
     }
     
+    pub fn children_mut(&mut self) -> &mut Vec<RgInstance> {
+        &mut self._children
+    }
+
     pub fn properties(&self) -> &Vec<RgProperty> {
         &self._properties
     }
 
+    pub fn properties_mut(&mut self) -> &mut Vec<RgProperty> {
+        &mut self._properties
+    }
+
+    // Replaces any property with the same name.
+    pub fn set_property(&mut self, prop: RgProperty) {
+        match self._properties.iter().position(|p| p.name == prop.name) {
+            Some(i) => self._properties[i] = prop,
+            None => self._properties.push(prop),
+        }
+    }
+
+    pub fn get_class_name(&self) -> &str {
+        self.get_prop_value("_ClassName").unwrap().as_str()
+    }
+
     pub fn get_prop_value(&self, name: &str) -> Option<&Value> {
         self.properties().iter()
             .find(|prop| prop.name == name)   
@@ -55,14 +229,61 @@ impl RgInstance {
     }
 }
 
-#[derive(PartialEq, Debug, Serialize)]
+#[derive(PartialEq, Debug, Serialize, Clone)]
 pub enum Value {
     StringLiteral(String),
     Number(f64),
     Boolean(bool),
     Color(i64, i64, i64),
     UDim2(f64, f64, f64, f64),
-    EnumMember(String),
+    UDim(f64, f64),
+    Vector2(f64, f64),
+    Vector3(f64, f64, f64),
+    Rect(f64, f64, f64, f64),
+    // Keypoints as a time from 0 to 1 and the value at that time
+    NumberSequence(Vec<(f64, f64)>),
+    ColorSequence(Vec<(f64, (i64, i64, i64))>),
+    NumberRange(f64, f64),
+    EnumMember(EnumMember),
+    // The id of another instance in the same file
+    Reference(String),
+}
+
+// A member written as just its name or qualified with its enum, as in
+// Enum.TextXAlignment.Center.
+#[derive(PartialEq, Debug, Serialize, Clone)]
+pub struct EnumMember {
+    name: String,
+    qualifier: Option<String>,
+    // The enum of the property it was assigned to and the member's value
+    // there, once checked against the api
+    resolved: Option<(String, u32)>,
+}
+
+impl EnumMember {
+    pub fn new(name: String) -> EnumMember {
+        EnumMember { name, qualifier: None, resolved: None }
+    }
+
+    pub fn qualified(enum_name: String, name: String) -> EnumMember {
+        EnumMember { name, qualifier: Some(enum_name), resolved: None }
+    }
+
+    pub fn with_resolution(self, enum_name: String, value: u32) -> EnumMember {
+        EnumMember { resolved: Some((enum_name, value)), ..self }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn qualifier(&self) -> Option<&str> {
+        self.qualifier.as_deref()
+    }
+
+    pub fn resolved(&self) -> Option<(&str, u32)> {
+        self.resolved.as_ref().map(|(enum_name, value)| (enum_name.as_str(), *value))
+    }
 }
 
 impl Value {
@@ -73,7 +294,15 @@ impl Value {
             Value::Boolean(_) => "Boolean",
             Value::Color(_, _, _) => "Color",
             Value::UDim2(_, _, _, _) => "UDim2",
+            Value::UDim(_, _) => "UDim",
+            Value::Vector2(_, _) => "Vector2",
+            Value::Vector3(_, _, _) => "Vector3",
+            Value::Rect(_, _, _, _) => "Rect",
+            Value::NumberSequence(_) => "NumberSequence",
+            Value::ColorSequence(_) => "ColorSequence",
+            Value::NumberRange(_, _) => "NumberRange",
             Value::EnumMember(_) => "EnumMember",
+            Value::Reference(_) => "Reference",
         }
     }
 
@@ -83,4 +312,72 @@ impl Value {
             _ => panic!("Not a string {:?}", self),
         }
     }
-}
\ No newline at end of file
+
+    // String literals are stored as written, this resolves their escape sequences.
+    pub fn unescaped_str(&self) -> String {
+        let mut unescaped = String::new();
+        let mut chars = self.as_str().chars();
+
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                unescaped.push(c);
+                continue;
+            }
+
+            match chars.next() {
+                Some('a') => unescaped.push('\u{07}'),
+                Some('b') => unescaped.push('\u{08}'),
+                Some('f') => unescaped.push('\u{0C}'),
+                Some('n') => unescaped.push('\n'),
+                Some('r') => unescaped.push('\r'),
+                Some('t') => unescaped.push('\t'),
+                Some('v') => unescaped.push('\u{0B}'),
+                Some(other) => unescaped.push(other),
+                None => unescaped.push('\\'),
+            }
+        }
+
+        unescaped
+    }
+}
+
+// Turns text into the form string literals are stored in, the reverse of unescaped_str.
+pub fn escape_str(text: &str) -> String {
+    let mut escaped = String::new();
+
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\u{07}' => escaped.push_str("\\a"),
+            '\u{08}' => escaped.push_str("\\b"),
+            '\u{0C}' => escaped.push_str("\\f"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\u{0B}' => escaped.push_str("\\v"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+impl fmt::Display for EnumMember {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.qualifier {
+            Some(ref enum_name) => write!(f, "Enum.{}.{}", enum_name, self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+impl fmt::Display for PropertyOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PropertyOrigin::Inline => write!(f, "inline"),
+            PropertyOrigin::Style(ref selector, position) =>
+                write!(f, "style rule #{} `{}`", position + 1, selector),
+        }
+    }
+}
@@ -1,74 +1,187 @@
-use std::collections::HashSet;
+use std::collections::{HashSet, HashMap};
 use rg::Value;
+use rg::values::EnumMember;
 
 pub struct RobloxApi {
     instances: Vec<Instance>,
     enums: Vec<Enum>,
+    instance_index: HashMap<String, usize>,
+    enum_index: HashMap<String, usize>,
+    // Every property of each class, inherited ones included, as the index of
+    // the class declaring it and its index there. Empty until index_members.
+    members: Vec<HashMap<String, (usize, usize)>>,
 }
 
 impl RobloxApi {
-    pub fn get_property<'a>(&'a self, inst: &'a Instance, prop: &'a str) -> Option<&Property> {
+    pub fn get_property<'a>(&'a self, inst: &'a Instance, prop: &str) -> Option<&'a Property> {
+        match self.instance_index.get(inst.get_name()).and_then(|class| self.members.get(*class)) {
+            Some(members) => members.get(prop)
+                .map(|(class, index)| &self.instances[*class].properties[*index]),
+            None => self.find_property(inst, prop),
+        }
+    }
+
+    // Walks up the superclass chain, for while the api is still being loaded.
+    fn find_property<'a>(&'a self, inst: &'a Instance, prop: &str) -> Option<&'a Property> {
         inst.get_property(prop).or_else(|| {
-            match inst.get_superclass() {
-                Some(ref sp) => self.get_property(
-                    self.get_instance(sp).unwrap(), prop),
-                None => None,
-            }
+            inst.get_superclass().as_ref()
+                .and_then(|sp| self.get_instance(sp))
+                .and_then(|superclass| self.find_property(superclass, prop))
         })
     }
 
+    // Flattens the properties of every class, so looking one up doesn't walk the
+    // superclass chain. Called once all classes are added, as adding or changing
+    // one throws the tables away.
+    pub fn index_members(&mut self) {
+        self.members = (0..self.instances.len()).map(|class| self.flatten_members(class)).collect();
+    }
+
+    fn flatten_members(&self, class: usize) -> HashMap<String, (usize, usize)> {
+        let mut members = HashMap::new();
+        let mut next = Some(class);
+
+        while let Some(class) = next {
+            for (index, prop) in self.instances[class].properties.iter().enumerate() {
+                members.entry(prop.get_name().to_owned()).or_insert((class, index));
+            }
+
+            next = self.instances[class].get_superclass().as_ref()
+                .and_then(|sp| self.instance_index.get(sp))
+                .cloned();
+        }
+
+        members
+    }
+
+    // Every property the instance has, its own first and then inherited ones.
+    pub fn get_properties<'a>(&'a self, inst: &'a Instance) -> Vec<&'a Property> {
+        let mut props: Vec<&Property> = inst.properties.iter().collect();
+
+        if let Some(superclass) = inst.get_superclass().as_ref().and_then(|sp| self.get_instance(sp)) {
+            props.extend(self.get_properties(superclass));
+        }
+
+        props
+    }
+
+    // Whether the class is the ancestor or inherits from it.
+    pub fn inherits(&self, class_name: &str, ancestor: &str) -> bool {
+        let mut class = Some(class_name);
+
+        while let Some(name) = class {
+            if name == ancestor {
+                return true;
+            }
+            class = self.get_instance(name).and_then(|inst| inst.get_superclass().as_deref());
+        }
+
+        false
+    }
+
     pub fn add_instance(&mut self, inst: Instance) {
+        self.members.clear();
+        self.instance_index.entry(inst.get_name().to_owned()).or_insert(self.instances.len());
         self.instances.push(inst)
     }
 
     pub fn get_instance(&self, name: &str) -> Option<&Instance> {
-        self.instances.iter()
-            .find(|inst| inst.get_name() == name)
+        self.instance_index.get(name).map(|index| &self.instances[*index])
     }
-    
+
     pub fn get_instance_mut(&mut self, name: &str) -> Option<&mut Instance> {
-        self.instances.iter_mut()
-            .find(|inst| inst.get_name() == name)
+        self.members.clear();
+
+        match self.instance_index.get(name) {
+            Some(index) => Some(&mut self.instances[*index]),
+            None => None,
+        }
+    }
+
+    pub fn get_instances(&self) -> &Vec<Instance> {
+        &self.instances
     }
 
     pub fn add_enum(&mut self, enm: Enum) {
+        self.enum_index.entry(enm.get_name().to_owned()).or_insert(self.enums.len());
         self.enums.push(enm)
     }
 
+    pub fn get_enums(&self) -> &Vec<Enum> {
+        &self.enums
+    }
+
     pub fn get_enum(&self, name: &str) -> Option<&Enum> {
-        self.enums.iter()
-            .find(|enm| enm.get_name() == name)
+        self.enum_index.get(name).map(|index| &self.enums[*index])
     }
 
     pub fn get_enum_mut(&mut self, name: &str) -> Option<&mut Enum> {
-        self.enums.iter_mut()
-            .find(|enm| enm.get_name() == name)
+        match self.enum_index.get(name) {
+            Some(index) => Some(&mut self.enums[*index]),
+            None => None,
+        }
     }
 
     pub fn new() -> RobloxApi {
-        RobloxApi { instances: vec![], enums: vec![] }
+        RobloxApi {
+            instances: vec![], enums: vec![],
+            instance_index: HashMap::new(), enum_index: HashMap::new(),
+            members: vec![],
+        }
     }
 }
 
+// Not every type uses every method.
 macro_rules! impl_base_methods {
     ($s: ident) => {
+        #[allow(dead_code)]
         impl $s {
-            pub fn get_name<'a>(&'a self) -> &'a str { &self.name }
+            pub fn get_name(&self) -> &str { &self.name }
 
             pub fn has_tag(&self, tag: &str) -> bool {
                 self.tags.contains(tag)
             }
+
+            pub fn has_tag_containing(&self, text: &str) -> bool {
+                self.tags.iter().any(|tag| tag.contains(text))
+            }
+
+            // Sorted, as the order they were loaded in isn't kept.
+            pub fn get_tags(&self) -> Vec<&str> {
+                let mut tags: Vec<&str> = self.tags.iter().map(|tag| tag.as_str()).collect();
+                tags.sort();
+                tags
+            }
         }
     }
 }
 
+// Types .rg has values for are listed one by one, everything else falls into
+// a category along with the name the api gives the type.
+#[derive(PartialEq, Debug)]
 pub enum PropertyType {
     String,
-    Number,
+    Content,
+    Int,
+    Int64,
+    Float,
+    Double,
     Boolean,
     Color,
     UDim2,
-    Other(String), // can contain enum name
+    UDim,
+    Vector2,
+    Vector3,
+    Rect,
+    NumberSequence,
+    ColorSequence,
+    NumberRange,
+    // A member of the named enum
+    Enum(String),
+    // A reference to another instance of the named class
+    Instance(String),
+    // A data type .rg has no values for, such as BrickColor or CoordinateFrame
+    DataType(String),
 }
 
 pub struct Property {
@@ -82,22 +195,34 @@ impl Property {
         Property { name, tags, val_type}
     }
 
+    pub fn get_type(&self) -> &PropertyType {
+        &self.val_type
+    }
+
     pub fn can_take_value<'a>(&'a self, api: &'a RobloxApi, val: &'a Value) -> bool {
         match &self.val_type {
-            PropertyType::String => val.get_type() == "String",
-            PropertyType::Number => val.get_type() == "Number",
+            PropertyType::String | PropertyType::Content => val.get_type() == "String",
+            PropertyType::Int | PropertyType::Int64 | PropertyType::Float | PropertyType::Double
+                => val.get_type() == "Number",
             PropertyType::Boolean => val.get_type() == "Boolean",
             PropertyType::Color => val.get_type() == "Color",
             PropertyType::UDim2 => val.get_type() == "UDim2",
-            PropertyType::Other(ref enum_name) => {
-                let roblox_enum = api.get_enum(enum_name).unwrap();
-
-                match val {
-                    Value::StringLiteral(ref member) | Value::EnumMember(ref member)
-                        => roblox_enum.has_member(member),
-                    _ => false,
-                }
-            }
+            PropertyType::UDim => val.get_type() == "UDim",
+            PropertyType::Vector2 => val.get_type() == "Vector2",
+            PropertyType::Vector3 => val.get_type() == "Vector3",
+            PropertyType::Rect => val.get_type() == "Rect",
+            PropertyType::NumberSequence => val.get_type() == "NumberSequence",
+            PropertyType::ColorSequence => val.get_type() == "ColorSequence",
+            PropertyType::NumberRange => val.get_type() == "NumberRange",
+            PropertyType::Enum(ref enum_name) => match val {
+                Value::EnumMember(ref member) => api.get_enum(enum_name)
+                    .and_then(|roblox_enum| roblox_enum.resolve(member))
+                    .is_some(),
+                _ => false,
+            },
+            // Whether the instance referred to is of the right class depends on the file
+            PropertyType::Instance(_) => val.get_type() == "Reference",
+            PropertyType::DataType(_) => false,
         }
     }
 }
@@ -106,46 +231,136 @@ pub struct Instance {
     name: String, tags: HashSet<String>,
     superclass: Option<String>,
     properties: Vec<Property>,
+    property_index: HashMap<String, usize>,
 }
 
 impl_base_methods!(Instance);
 impl Instance {
     pub fn new(name: String, tags: HashSet<String>, superclass: Option<String>) -> Instance {
-        Instance { name, tags, superclass, properties: vec![] }
+        Instance { name, tags, superclass, properties: vec![], property_index: HashMap::new() }
     }
 
     pub fn add_property(&mut self, prop: Property) {
+        self.property_index.entry(prop.get_name().to_owned()).or_insert(self.properties.len());
         self.properties.push(prop)
     }
 
     fn get_property(&self, prop: &str) -> Option<&Property> {
-        self.properties.iter()
-            .find(|p| (*p).get_name() == prop)
+        self.property_index.get(prop).map(|index| &self.properties[*index])
     }
 
     pub fn get_superclass(&self) -> &Option<String> {
         &self.superclass
     }
 
+    pub fn set_superclass(&mut self, superclass: Option<String>) {
+        self.superclass = superclass;
+    }
 }
 
+// Nothing reads an enum's tags yet, they are kept like those of classes.
+#[allow(dead_code)]
 pub struct Enum {
     name: String, tags: HashSet<String>,
-    members: HashSet<String>,
+    members: HashMap<String, u32>,
+    // The first member added with each value
+    names: HashMap<u32, String>,
 }
 
 impl_base_methods!(Enum);
 impl Enum {
     pub fn new(name: String, tags: HashSet<String>) -> Enum {
-        Enum { name, tags, members: HashSet::new() }
+        Enum { name, tags, members: HashMap::new(), names: HashMap::new() }
+    }
+
+    pub fn add_member(&mut self, member: String, value: u32) {
+        self.names.entry(value).or_insert_with(|| member.clone());
+        self.members.insert(member, value);
+    }
+
+    pub fn get_member_value(&self, member: &str) -> Option<u32> {
+        self.members.get(member).cloned()
     }
 
-    pub fn add_member(&mut self, member: String) {
-        self.members.insert(member);
+    // The member's value, as long as it isn't qualified with another enum.
+    pub fn resolve(&self, member: &EnumMember) -> Option<u32> {
+        match member.qualifier() {
+            Some(qualifier) if qualifier != self.name => None,
+            _ => self.get_member_value(member.name()),
+        }
     }
 
-    pub fn has_member(&self, member: &str) -> bool {
-        self.members.contains(member)
+    pub fn get_member_names(&self) -> Vec<&str> {
+        self.members.keys().map(|member| member.as_str()).collect()
+    }
+
+    pub fn get_member_name(&self, value: u32) -> Option<&str> {
+        self.names.get(&value).map(|member| member.as_str())
     }
 }
 
+
+#[cfg(test)]
+mod can_look_up {
+    use super::*;
+
+    fn class(name: &str, superclass: Option<&str>, props: Vec<(&str, PropertyType)>) -> Instance {
+        let mut inst = Instance::new(name.to_owned(), HashSet::new(), superclass.map(|sp| sp.to_owned()));
+
+        for (prop_name, prop_type) in props {
+            inst.add_property(Property::new(prop_name.to_owned(), HashSet::new(), prop_type));
+        }
+
+        inst
+    }
+
+    fn api() -> RobloxApi {
+        let mut api = RobloxApi::new();
+
+        // Subclasses can come before the class they inherit from
+        api.add_instance(class("TextLabel", Some("GuiObject"), vec![("Text", PropertyType::String)]));
+        api.add_instance(class("GuiObject", Some("Instance"), vec![("Name", PropertyType::Float)]));
+        api.add_instance(class("Instance", None, vec![("Name", PropertyType::String), ("Parent", PropertyType::String)]));
+        api
+    }
+
+    fn property_types(api: &RobloxApi) -> Vec<Option<&PropertyType>> {
+        let label = api.get_instance("TextLabel").unwrap();
+
+        ["Text", "Name", "Parent", "Size"].iter()
+            .map(|name| api.get_property(label, name).map(|prop| prop.get_type()))
+            .collect()
+    }
+
+    #[test]
+    fn inherited_properties() {
+        let walked = api();
+        let mut indexed = api();
+        indexed.index_members();
+
+        assert_eq!(property_types(&walked),
+            vec![Some(&PropertyType::String), Some(&PropertyType::Float), Some(&PropertyType::String), None]);
+        assert_eq!(property_types(&indexed), property_types(&walked));
+    }
+
+    #[test]
+    fn properties_added_after_indexing() {
+        let mut api = api();
+        api.index_members();
+        api.get_instance_mut("Instance").unwrap()
+            .add_property(Property::new("Size".to_owned(), HashSet::new(), PropertyType::UDim2));
+
+        assert_eq!(property_types(&api)[3], Some(&PropertyType::UDim2));
+    }
+
+    #[test]
+    fn enum_members_both_ways() {
+        let mut alignment = Enum::new("TextXAlignment".to_owned(), HashSet::new());
+        alignment.add_member("Left".to_owned(), 0);
+        alignment.add_member("Center".to_owned(), 2);
+
+        assert_eq!(alignment.get_member_value("Center"), Some(2));
+        assert_eq!(alignment.get_member_name(0), Some("Left"));
+        assert_eq!(alignment.get_member_name(1), None);
+    }
+}
@@ -0,0 +1,86 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// Where downloaded api dumps are kept, shared by every project of the user.
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Caches"))
+    } else {
+        env::var_os("XDG_CACHE_HOME").map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+    };
+
+    base.map(|dir| dir.join("rgss"))
+}
+
+fn file_name(version: &str) -> String {
+    let version: String = version.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '.')
+        .collect();
+
+    format!("api-{}.json", version)
+}
+
+pub fn save(dir: &Path, version: &str, text: &str) -> Result<PathBuf, String> {
+    let path = dir.join(file_name(version));
+
+    fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&path, text))
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    Ok(path)
+}
+
+fn is_api_file(path: &Path) -> bool {
+    match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name.starts_with("api-") && name.ends_with(".json"),
+        None => false,
+    }
+}
+
+// The most recently downloaded dump, whichever version it is.
+pub fn newest(dir: &Path) -> Option<PathBuf> {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).unwrap_or(SystemTime::UNIX_EPOCH);
+
+    fs::read_dir(dir).ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| is_api_file(path))
+        .max_by_key(|path| (modified(path), path.clone()))
+}
+
+#[cfg(test)]
+mod can_cache {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("rgss-cache-{}-{}", name, ::std::process::id()))
+    }
+
+    #[test]
+    fn key_by_version() {
+        assert_eq!(file_name("version-5f7bcf3d"), "api-version-5f7bcf3d.json");
+        assert_eq!(file_name("../version/x"), "api-..versionx.json");
+    }
+
+    #[test]
+    fn find_saved_dump() {
+        let dir = temp_dir("saved");
+        let path = save(&dir, "version-1", "[]").unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+
+        let newest = newest(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(newest, Some(path));
+    }
+
+    #[test]
+    fn empty_cache() {
+        assert_eq!(newest(&temp_dir("missing")), None);
+    }
+}
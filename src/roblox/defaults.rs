@@ -0,0 +1,99 @@
+use rg::Value;
+use rg::values::EnumMember;
+use roblox::RobloxApi;
+
+fn member(name: &str) -> Value {
+    Value::EnumMember(EnumMember::new(name.to_owned()))
+}
+
+fn string(text: &str) -> Value {
+    Value::StringLiteral(text.to_owned())
+}
+
+// The values a property has straight after Instance.new, for the classes it
+// is declared on. The api dump has no defaults, so only properties GUIs commonly
+// set are listed and anything missing is treated as having no known default.
+fn declared_default(class_name: &str, prop: &str) -> Option<Value> {
+    let value = match (class_name, prop) {
+        ("Instance", "Archivable") => Value::Boolean(true),
+
+        ("GuiObject", "Active") => Value::Boolean(false),
+        ("GuiObject", "AnchorPoint") => Value::Vector2(0.0, 0.0),
+        ("GuiObject", "BackgroundColor3") => Value::Color(163, 162, 165),
+        ("GuiObject", "BackgroundTransparency") => Value::Number(0.0),
+        ("GuiObject", "BorderColor3") => Value::Color(27, 42, 53),
+        ("GuiObject", "BorderSizePixel") => Value::Number(1.0),
+        ("GuiObject", "ClipsDescendants") => Value::Boolean(false),
+        ("GuiObject", "Draggable") => Value::Boolean(false),
+        ("GuiObject", "LayoutOrder") => Value::Number(0.0),
+        ("GuiObject", "Position") => Value::UDim2(0.0, 0.0, 0.0, 0.0),
+        ("GuiObject", "Rotation") => Value::Number(0.0),
+        ("GuiObject", "Selectable") => Value::Boolean(false),
+        ("GuiObject", "Size") => Value::UDim2(0.0, 0.0, 0.0, 0.0),
+        ("GuiObject", "SizeConstraint") => member("RelativeXY"),
+        ("GuiObject", "Visible") => Value::Boolean(true),
+        ("GuiObject", "ZIndex") => Value::Number(1.0),
+
+        ("Frame", "Style") => member("Custom"),
+
+        ("GuiButton", "AutoButtonColor") => Value::Boolean(true),
+        ("GuiButton", "Modal") => Value::Boolean(false),
+        ("GuiButton", "Selected") => Value::Boolean(false),
+        ("GuiButton", "Style") => member("Custom"),
+
+        ("TextBox", "ClearTextOnFocus") => Value::Boolean(true),
+        ("TextBox", "MultiLine") => Value::Boolean(false),
+        ("TextLabel", "Text") => string("Label"),
+        ("TextButton", "Text") => string("Button"),
+        ("TextLabel", _) | ("TextButton", _) | ("TextBox", _) => match prop {
+            "Font" => member("Legacy"),
+            "LineHeight" => Value::Number(1.0),
+            "TextColor3" => Value::Color(27, 42, 53),
+            "TextScaled" => Value::Boolean(false),
+            "TextStrokeColor3" => Value::Color(0, 0, 0),
+            "TextStrokeTransparency" => Value::Number(1.0),
+            "TextTransparency" => Value::Number(0.0),
+            "TextWrapped" => Value::Boolean(false),
+            "TextXAlignment" => member("Center"),
+            "TextYAlignment" => member("Center"),
+            _ => return None,
+        },
+
+        ("ImageLabel", _) | ("ImageButton", _) => match prop {
+            "Image" => string(""),
+            "ImageColor3" => Value::Color(255, 255, 255),
+            "ImageTransparency" => Value::Number(0.0),
+            "ScaleType" => member("Stretch"),
+            "SliceCenter" => Value::Rect(0.0, 0.0, 0.0, 0.0),
+            _ => return None,
+        },
+
+        ("ScrollingFrame", "CanvasPosition") => Value::Vector2(0.0, 0.0),
+        ("ScrollingFrame", "CanvasSize") => Value::UDim2(0.0, 0.0, 2.0, 0.0),
+        ("ScrollingFrame", "ScrollBarThickness") => Value::Number(12.0),
+        ("ScrollingFrame", "ScrollingEnabled") => Value::Boolean(true),
+
+        ("LayerCollector", "Enabled") => Value::Boolean(true),
+        ("LayerCollector", "ResetOnSpawn") => Value::Boolean(true),
+        ("ScreenGui", "DisplayOrder") => Value::Number(0.0),
+
+        _ => return None,
+    };
+
+    Some(value)
+}
+
+// Walks up the superclasses to the class declaring the default.
+pub fn get_default(api: &RobloxApi, class_name: &str, prop: &str) -> Option<Value> {
+    let mut class = api.get_instance(class_name);
+
+    while let Some(inst) = class {
+        if let Some(value) = declared_default(inst.get_name(), prop) {
+            return Some(value);
+        }
+
+        class = inst.get_superclass().as_ref().and_then(|superclass| api.get_instance(superclass));
+    }
+
+    None
+}
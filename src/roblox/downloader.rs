@@ -1,34 +1,24 @@
 extern crate reqwest;
 
-use std;
+const VERSION_URL: &str = "https://setup.rbxcdn.com/versionQTStudio";
+const API_DUMP_URL: &str = "https://setup.rbxcdn.com";
 
-fn try_download_http() -> reqwest::Result<String> {
-    reqwest::get("https://anaminus.github.io/rbx/json/api/latest.json")?.text()
-}
+fn fetch(url: &str) -> Result<String, String> {
+    let mut response = reqwest::get(url)
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
 
-fn get_api_dir() -> std::path::PathBuf {
-    let mut cur_path = std::env::current_dir().unwrap();
-    
-    cur_path.push("api.json");
+    if !response.status().is_success() {
+        return Err(format!("Failed to download {}", url));
+    }
 
-    cur_path
+    response.text().map_err(|e| format!("Failed to download {}: {}", url, e))
 }
 
-pub fn get_api_string() -> Option<String> {
-    match try_download_http() {
-        Ok(json_text) => {
-            save_api_to_file(&json_text);
-
-            Some(json_text)
-        }
-        Err(_) => {
-            println!("Failed to download latest api, will try and use local file");
-
-            std::fs::read_to_string(get_api_dir()).ok()
-        }
-    }
+// The version of Roblox currently deployed, e.g. version-5f7bcf3d4e2c4a1b
+pub fn latest_version() -> Result<String, String> {
+    fetch(VERSION_URL).map(|version| version.trim().to_owned())
 }
 
-fn save_api_to_file(text: &str) {
-    std::fs::write(get_api_dir(), text).expect("failed to write file");
-}
\ No newline at end of file
+pub fn download_api(version: &str) -> Result<String, String> {
+    fetch(&format!("{}/{}-API-Dump.json", API_DUMP_URL, version))
+}
@@ -1,12 +1,174 @@
 mod downloader;
-mod api;
+mod cache;
+pub mod api;
 mod parser;
+pub mod defaults;
 
-pub use self::api::RobloxApi;
+use std::fs;
+use std::path::Path;
 
-pub fn initalise() -> Result<RobloxApi, &'static str> {
-    match downloader::get_api_string() {
-        Some(text) => Ok(parser::load_api(&text)),
-        None => Err("failed to find a copy of the roblox api")
+pub use self::api::{RobloxApi, PropertyType};
+use self::parser::ParseMode;
+
+// Where the compiler looks for the api. It only goes online when asked to.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ApiMode {
+    // Use the newest downloaded dump, or else the one built in
+    PreferCache,
+    // Use the one built in, ignoring the cache and never touching the network
+    Offline,
+    // Download the dump of the current Roblox version
+    Update,
+}
+
+// The pinned api.json, compiled by build.rs.
+const SNAPSHOT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/api.snapshot"));
+
+fn built_in() -> Result<RobloxApi, String> {
+    parser::load_snapshot(SNAPSHOT).map_err(|e| format!("The built in api is broken: {}", e))
+}
+
+// Only the first few entries a newer dump has which rgss can't read are listed.
+const MAX_WARNINGS: usize = 5;
+
+fn parse_api(text: &str, source: &str, mode: ParseMode) -> Result<RobloxApi, String> {
+    let (api, warnings) = parser::load_api(text, mode)
+        .map_err(|e| format!("Failed to read api {}: {}", source, e))?;
+
+    for warning in warnings.iter().take(MAX_WARNINGS) {
+        eprintln!("warning: api {}: {}", source, warning);
+    }
+
+    if warnings.len() > MAX_WARNINGS {
+        eprintln!("warning: api {}: {} more entries were skipped", source, warnings.len() - MAX_WARNINGS);
+    }
+
+    Ok(api)
+}
+
+fn read_api(path: &Path, mode: ParseMode) -> Result<RobloxApi, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read api {}: {}", path.display(), e))?;
+
+    parse_api(&text, &path.display().to_string(), mode)
+}
+
+fn download(cache_dir: Option<&Path>) -> Result<RobloxApi, String> {
+    let version = downloader::latest_version()?;
+    let text = downloader::download_api(&version)?;
+
+    match cache_dir.map(|dir| cache::save(dir, &version, &text)) {
+        Some(Err(error)) => eprintln!("warning: failed to cache the api: {}", error),
+        None => eprintln!("warning: no cache directory, the api will be downloaded again next time"),
+        Some(Ok(_)) => {}
+    }
+
+    parse_api(&text, &format!("version {}", version), ParseMode::Tolerant)
+}
+
+// Loads the api a project pinned, otherwise the one in the user's cache, or
+// the one built in when nothing has been downloaded or rgss is offline. A pinned api must be read
+// in full, while downloaded ones skip what they can't read so a change to the
+// dump Roblox publishes can't stop rgss from working.
+pub fn load(mode: ApiMode, pinned: Option<&Path>) -> Result<RobloxApi, String> {
+    if let Some(path) = pinned {
+        if mode == ApiMode::Update {
+            return Err(format!("The api is pinned to {}, --update-api would not be used", path.display()));
+        }
+
+        return read_api(path, ParseMode::Strict);
+    }
+
+    if mode == ApiMode::Offline {
+        return built_in();
+    }
+
+    let cache_dir = cache::cache_dir();
+    let cached = cache_dir.as_ref().and_then(|dir| cache::newest(dir));
+
+    match (mode, cached) {
+        (ApiMode::Update, _) => download(cache_dir.as_deref()),
+        (_, Some(path)) => read_api(&path, ParseMode::Tolerant),
+        (_, None) => built_in(),
+    }
+}
+
+#[cfg(test)]
+mod can_load {
+    use super::*;
+
+    #[test]
+    fn offline_without_the_cache() {
+        let api = load(ApiMode::Offline, None).unwrap();
+
+        assert!(api.get_instance("TextLabel").is_some());
+    }
+
+    #[test]
+    fn pinned_offline() {
+        let pinned = Path::new(env!("CARGO_MANIFEST_DIR")).join("api.json");
+
+        assert!(load(ApiMode::Offline, Some(&pinned)).unwrap().get_instance("TextLabel").is_some());
+        assert!(load(ApiMode::Update, Some(&pinned)).is_err());
+    }
+}
+
+// Compiles a generated file of a few thousand instances against the built in
+// api. Run with `cargo test --release compile_large_file -- --ignored --nocapture`.
+#[cfg(test)]
+mod benchmark {
+    use std::time::Instant;
+    use rg::parser::parse_str;
+    use rg::styles::StyleSheet;
+    use codegen::{self, Format};
+    use super::parser::{load_api, ParseMode};
+
+    const FRAMES: usize = 250;
+    const LABELS: usize = 12;
+
+    fn generated_source() -> String {
+        let mut source = String::from("ScreenGui \"Generated\" {\n");
+
+        for frame in 0..FRAMES {
+            source.push_str(&format!("    Frame \"Frame{}\" {{\n        Size: UDim2(1, 0, 0, 40)\n        \
+                BackgroundColor3: #202020\n        ZIndex: {}\n", frame, frame % 10));
+
+            for label in 0..LABELS {
+                source.push_str(&format!("        TextLabel \"Label{}\" {{\n            Text: \"{} {}\"\n            \
+                    TextColor3: RGB(255, 255, 255)\n            TextXAlignment: Left\n            \
+                    BackgroundTransparency: 1\n            Visible: true\n        }}\n", label, frame, label));
+            }
+
+            source.push_str("    }\n");
+        }
+
+        source.push_str("}\n");
+        source
+    }
+
+    #[test]
+    #[ignore]
+    fn compile_large_file() {
+        let start = Instant::now();
+        load_api(include_str!("../../api.json"), ParseMode::Strict).unwrap();
+        println!("parsed api.json in {:?}", start.elapsed());
+
+        let start = Instant::now();
+        let api = super::built_in().unwrap();
+        println!("loaded the built in api in {:?}", start.elapsed());
+
+        let source = generated_source();
+        let styles = StyleSheet::default();
+
+        let start = Instant::now();
+        let (nodes, diagnostics) = parse_str(&source, &styles, &api);
+        println!("checked {} instances in {:?}", FRAMES * (LABELS + 1) + 1, start.elapsed());
+        assert!(!diagnostics.has_errors());
+
+        for format in [Format::Lua, Format::Rbxmx, Format::Rbxm, Format::Json].iter() {
+            let start = Instant::now();
+            codegen::generate(&api, &nodes, *format).unwrap();
+            println!("generated {} in {:?}", format.extension(), start.elapsed());
+        }
     }
 }
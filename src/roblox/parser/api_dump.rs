@@ -0,0 +1,430 @@
+use super::serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use roblox::api::*;
+use super::serde::Deserialize;
+use super::{value_type, Skipped};
+
+const ROOT_CLASS: &str = "<<<ROOT>>>";
+
+// Members of a class other than properties, which rgss has no use for.
+const IGNORED_MEMBERS: &[&str] = &["Function", "Event", "Callback"];
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Dump {
+    classes: Vec<Value>,
+    enums: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DumpClass {
+    name: String,
+    superclass: String,
+    #[serde(default)]
+    tags: Vec<Value>,
+    members: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DumpMember {
+    member_type: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DumpProperty {
+    name: String,
+    #[serde(default)]
+    tags: Vec<Value>,
+    security: Option<Security>,
+    value_type: DumpValueType,
+}
+
+// The flat format lists any security as a tag, the dump has a read and a write
+// security or, in older dumps, a single one.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Security {
+    Single(String),
+    #[serde(rename_all = "PascalCase")]
+    Access { read: Option<String>, write: Option<String> },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DumpValueType {
+    category: Option<String>,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DumpEnum {
+    name: String,
+    #[serde(default)]
+    tags: Vec<Value>,
+    items: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DumpEnumItem {
+    name: String,
+    value: u32,
+}
+
+// The dump capitalises the tags the flat format spells in lower case.
+fn flat_tag(tag: &str) -> String {
+    match tag {
+        "NotCreatable" => "notCreatable".to_owned(),
+        "ReadOnly" => "readonly".to_owned(),
+        "Hidden" => "hidden".to_owned(),
+        "Deprecated" => "deprecated".to_owned(),
+        "NotBrowsable" => "notbrowsable".to_owned(),
+        "NotScriptable" => "notscriptable".to_owned(),
+        _ => tag.to_owned(),
+    }
+}
+
+// Newer dumps mix objects such as PreferredDescriptor in with the string tags.
+fn parse_tags(tags: &[Value]) -> HashSet<String> {
+    tags.iter().filter_map(|tag| tag.as_str()).map(flat_tag).collect()
+}
+
+fn add_security_tags(tags: &mut HashSet<String>, security: Option<Security>) {
+    let securities = match security {
+        Some(Security::Single(security)) => vec![security],
+        Some(Security::Access { read, write }) => read.into_iter().chain(write).collect(),
+        None => vec![],
+    };
+
+    tags.extend(securities.into_iter().filter(|s| s != "None"));
+}
+
+fn parse_value_type(val_type: DumpValueType) -> PropertyType {
+    match val_type.category.as_deref() {
+        Some("Class") => PropertyType::Instance(val_type.name),
+        Some("Enum") => PropertyType::Enum(val_type.name),
+        _ => value_type(&val_type.name).unwrap_or(PropertyType::DataType(val_type.name)),
+    }
+}
+
+fn parse_member(instance: &mut Instance, member: &Value, path: &str, skipped: &mut Skipped) -> Result<(), String> {
+    let member_type = match skipped.read::<DumpMember>(member, path)? {
+        Some(member) => member.member_type,
+        None => return Ok(()),
+    };
+
+    match member_type.as_str() {
+        "Property" => if let Some(prop) = skipped.read::<DumpProperty>(member, path)? {
+            let mut tags = parse_tags(&prop.tags);
+            add_security_tags(&mut tags, prop.security);
+
+            instance.add_property(Property::new(prop.name, tags, parse_value_type(prop.value_type)));
+        },
+        other if IGNORED_MEMBERS.contains(&other) => {}
+        other => skipped.skip(path, format!("unknown member type {}", other))?,
+    }
+
+    Ok(())
+}
+
+fn parse_class(api: &mut RobloxApi, class: &Value, path: &str, class_paths: &mut HashMap<String, String>,
+    skipped: &mut Skipped) -> Result<(), String> {
+    let class = match skipped.read::<DumpClass>(class, path)? {
+        Some(class) => class,
+        None => return Ok(()),
+    };
+
+    class_paths.entry(class.name.clone()).or_insert_with(|| path.to_owned());
+
+    let superclass = Some(class.superclass).filter(|superclass| superclass != ROOT_CLASS);
+    let mut instance = Instance::new(class.name, parse_tags(&class.tags), superclass);
+
+    for (i, member) in class.members.iter().enumerate() {
+        parse_member(&mut instance, member, &format!("{}.Members[{}]", path, i), skipped)?;
+    }
+
+    api.add_instance(instance);
+    Ok(())
+}
+
+fn parse_enum(api: &mut RobloxApi, enm: &Value, path: &str, skipped: &mut Skipped) -> Result<(), String> {
+    let enm = match skipped.read::<DumpEnum>(enm, path)? {
+        Some(enm) => enm,
+        None => return Ok(()),
+    };
+
+    let mut roblox_enum = Enum::new(enm.name, parse_tags(&enm.tags));
+
+    for (i, item) in enm.items.iter().enumerate() {
+        if let Some(item) = skipped.read::<DumpEnumItem>(item, &format!("{}.Items[{}]", path, i))? {
+            roblox_enum.add_member(item.name, item.value);
+        }
+    }
+
+    api.add_enum(roblox_enum);
+    Ok(())
+}
+
+// Roblox's own API-Dump.json, where members are nested inside their class.
+pub fn parse_dump(api: &mut RobloxApi, dump: &Value, class_paths: &mut HashMap<String, String>,
+    skipped: &mut Skipped) -> Result<(), String> {
+    let dump = Dump::deserialize(dump).map_err(|e| format!("$: {}", e))?;
+
+    for (i, class) in dump.classes.iter().enumerate() {
+        parse_class(api, class, &format!("$.Classes[{}]", i), class_paths, skipped)?;
+    }
+
+    for (i, enm) in dump.enums.iter().enumerate() {
+        parse_enum(api, enm, &format!("$.Enums[{}]", i), skipped)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod can_load {
+    use roblox::parser::{load_api, ParseMode};
+    use roblox::api::{PropertyType, RobloxApi};
+
+    const DUMP: &str = r#"{
+        "Version": 1,
+        "Classes": [
+            { "Name": "Instance", "Superclass": "<<<ROOT>>>", "Tags": ["NotCreatable"], "Members": [
+                { "MemberType": "Property", "Name": "Name", "Security": { "Read": "None", "Write": "None" },
+                  "ValueType": { "Category": "Primitive", "Name": "string" } },
+                { "MemberType": "Function", "Name": "Destroy", "Security": "None", "Parameters": [] }
+            ] },
+            { "Name": "UICorner", "Superclass": "Instance", "Members": [
+                { "MemberType": "Property", "Name": "CornerRadius", "Security": { "Read": "None", "Write": "None" },
+                  "ValueType": { "Category": "DataType", "Name": "UDim" } }
+            ] },
+            { "Name": "TextLabel", "Superclass": "Instance", "Members": [
+                { "MemberType": "Property", "Name": "TextXAlignment", "Security": { "Read": "None", "Write": "None" },
+                  "ValueType": { "Category": "Enum", "Name": "TextXAlignment" } },
+                { "MemberType": "Property", "Name": "TextBounds", "Tags": ["ReadOnly", "NotReplicated"],
+                  "Security": { "Read": "None", "Write": "None" },
+                  "ValueType": { "Category": "DataType", "Name": "Vector2" } },
+                { "MemberType": "Property", "Name": "TextColor3", "Security": "None",
+                  "ValueType": { "Category": "DataType", "Name": "Color3" } },
+                { "MemberType": "Property", "Name": "LocalizedText", "Tags": ["Hidden", { "PreferredDescriptor": {} }],
+                  "Security": { "Read": "None", "Write": "RobloxScriptSecurity" },
+                  "ValueType": { "Category": "Primitive", "Name": "string" } },
+                { "MemberType": "Property", "Name": "NextSelectionUp", "Security": { "Read": "None", "Write": "None" },
+                  "ValueType": { "Category": "Class", "Name": "GuiObject" } },
+                { "MemberType": "Property", "Name": "BorderColor", "Security": { "Read": "None", "Write": "None" },
+                  "ValueType": { "Category": "DataType", "Name": "BrickColor" } }
+            ] }
+        ],
+        "Enums": [
+            { "Name": "TextXAlignment", "Items": [
+                { "Name": "Left", "Value": 0 },
+                { "Name": "Right", "Value": 1 },
+                { "Name": "Center", "Value": 2 }
+            ] }
+        ]
+    }"#;
+
+    fn load(json: &str) -> RobloxApi {
+        load_api(json, ParseMode::Strict).unwrap().0
+    }
+
+    macro_rules! test_property_type {
+        ($test_name: ident, $class: expr, $prop: expr, $expected: expr) => {
+            #[test]
+            fn $test_name() {
+                let api = load(DUMP);
+                let class = api.get_instance($class).unwrap();
+                let prop = api.get_property(class, $prop).unwrap();
+
+                assert_eq!(prop.get_type(), &$expected);
+            }
+        }
+    }
+
+    test_property_type!(primitive, "TextLabel", "Name", PropertyType::String);
+    test_property_type!(data_type, "TextLabel", "TextColor3", PropertyType::Color);
+    test_property_type!(udim_type, "UICorner", "CornerRadius", PropertyType::UDim);
+    test_property_type!(vector2_type, "TextLabel", "TextBounds", PropertyType::Vector2);
+    test_property_type!(enum_type, "TextLabel", "TextXAlignment", PropertyType::Enum("TextXAlignment".to_owned()));
+    test_property_type!(class_type, "TextLabel", "NextSelectionUp", PropertyType::Instance("GuiObject".to_owned()));
+    test_property_type!(other_data_type, "TextLabel", "BorderColor", PropertyType::DataType("BrickColor".to_owned()));
+
+    #[test]
+    fn class_hierarchy() {
+        let api = load(DUMP);
+
+        assert_eq!(api.get_instance("Instance").unwrap().get_superclass(), &None);
+        assert_eq!(api.get_instance("UICorner").unwrap().get_superclass(), &Some("Instance".to_owned()));
+        assert!(api.get_instance("Instance").unwrap().has_tag("notCreatable"));
+    }
+
+    #[test]
+    fn only_properties() {
+        let api = load(DUMP);
+
+        assert!(api.get_property(api.get_instance("Instance").unwrap(), "Destroy").is_none());
+    }
+
+    #[test]
+    fn member_tags() {
+        let api = load(DUMP);
+        let label = api.get_instance("TextLabel").unwrap();
+
+        assert!(api.get_property(label, "TextBounds").unwrap().has_tag("readonly"));
+        assert!(api.get_property(label, "LocalizedText").unwrap().has_tag("hidden"));
+        assert!(api.get_property(label, "LocalizedText").unwrap().has_tag("RobloxScriptSecurity"));
+    }
+
+    #[test]
+    fn enum_items() {
+        let api = load(DUMP);
+        let alignment = api.get_enum("TextXAlignment").unwrap();
+
+        assert_eq!(alignment.get_member_value("Right"), Some(1));
+        assert_eq!(alignment.get_member_name(2), Some("Center"));
+    }
+
+    #[test]
+    fn flat_format() {
+        let api = load(r#"[
+            { "type": "Class", "Name": "Instance", "tags": [] },
+            { "type": "Property", "Class": "Instance", "Name": "Name", "ValueType": "string", "tags": [] }
+        ]"#);
+
+        assert!(api.get_property(api.get_instance("Instance").unwrap(), "Name").is_some());
+    }
+
+    #[test]
+    fn flat_format_categories() {
+        let api = load(r#"[
+            { "type": "Class", "Name": "Instance", "tags": [] },
+            { "type": "Class", "Name": "Part", "Superclass": "Instance", "tags": [] },
+            { "type": "Property", "Class": "Part", "Name": "Shape", "ValueType": "PartType", "tags": [] },
+            { "type": "Property", "Class": "Part", "Name": "CFrame", "ValueType": "CoordinateFrame", "tags": [] },
+            { "type": "Property", "Class": "Part", "Name": "Parent", "ValueType": "Class:Instance", "tags": [] },
+            { "type": "Enum", "Name": "PartType", "tags": [] }
+        ]"#);
+        let part = api.get_instance("Part").unwrap();
+        let type_of = |name| api.get_property(part, name).unwrap().get_type();
+
+        assert_eq!(type_of("Shape"), &PropertyType::Enum("PartType".to_owned()));
+        assert_eq!(type_of("CFrame"), &PropertyType::DataType("CoordinateFrame".to_owned()));
+        assert_eq!(type_of("Parent"), &PropertyType::Instance("Instance".to_owned()));
+    }
+
+    #[test]
+    fn flat_format_rect2d() {
+        let api = load(r#"[
+            { "type": "Class", "Name": "ImageLabel", "tags": [] },
+            { "type": "Property", "Class": "ImageLabel", "Name": "SliceCenter", "ValueType": "Rect2D", "tags": [] }
+        ]"#);
+        let label = api.get_instance("ImageLabel").unwrap();
+
+        assert_eq!(api.get_property(label, "SliceCenter").unwrap().get_type(), &PropertyType::Rect);
+    }
+
+    #[test]
+    fn flat_format_in_any_order() {
+        let api = load(r#"[
+            { "type": "EnumItem", "Enum": "PartType", "Name": "Ball", "Value": 0 },
+            { "type": "Property", "Class": "Part", "Name": "Shape", "ValueType": "PartType", "tags": [] },
+            { "type": "Class", "Name": "Part", "Superclass": null, "tags": [] },
+            { "type": "Enum", "Name": "PartType", "tags": [] }
+        ]"#);
+        let part = api.get_instance("Part").unwrap();
+
+        assert_eq!(api.get_property(part, "Shape").unwrap().get_type(), &PropertyType::Enum("PartType".to_owned()));
+        assert_eq!(api.get_enum("PartType").unwrap().get_member_value("Ball"), Some(0));
+    }
+
+    macro_rules! test_load_error {
+        ($test_name: ident, $json: expr, $expected: expr) => {
+            #[test]
+            fn $test_name() {
+                match load_api($json, ParseMode::Strict) {
+                    Err(error) => assert_eq!(error, $expected),
+                    Ok(_) => panic!("Expected {} but the api loaded", $expected),
+                }
+            }
+        }
+    }
+
+    test_load_error!(not_json, "[", "Invalid JSON: EOF while parsing a list at line 1 column 1");
+    test_load_error!(not_a_dump, "{}", "$: expected an API dump with Classes or the flat array of entries");
+    test_load_error!(dump_without_enums, r#"{ "Classes": [] }"#, "$: missing field `Enums`");
+    test_load_error!(member_without_type, r#"{ "Classes": [{ "Name": "Instance", "Superclass": "<<<ROOT>>>",
+        "Members": [{ "MemberType": "Property", "Name": "Name" }] }], "Enums": [] }"#,
+        "$.Classes[0].Members[0]: missing field `ValueType`");
+    test_load_error!(item_with_text_value, r#"{ "Classes": [], "Enums": [{ "Name": "Font", "Items": [
+        { "Name": "Legacy", "Value": 0 }, { "Name": "Arial", "Value": "1" }] }] }"#,
+        "$.Enums[0].Items[1]: invalid type: string \"1\", expected u32");
+    test_load_error!(property_of_unknown_class, r#"[
+        { "type": "Property", "Class": "Part", "Name": "Size", "ValueType": "Vector3", "tags": [] }]"#,
+        "$[0]: property Size belongs to unknown class Part");
+    test_load_error!(item_of_unknown_enum, r#"[{ "type": "EnumItem", "Enum": "Font", "Name": "Arial", "Value": 1 }]"#,
+        "$[0]: item Arial belongs to unknown enum Font");
+    test_load_error!(unknown_entry_type, r#"[{ "type": "Signal", "Name": "Changed" }]"#,
+        "$[0]: unknown entry type Signal");
+    test_load_error!(unknown_superclass, r#"[
+        { "type": "Class", "Name": "Instance", "Superclass": null, "tags": [] },
+        { "type": "Class", "Name": "Frame", "Superclass": "GuiObject", "tags": [] }]"#,
+        "$[1]: class Frame inherits from unknown class GuiObject");
+    test_load_error!(superclass_cycle, r#"{ "Classes": [
+        { "Name": "Instance", "Superclass": "<<<ROOT>>>", "Members": [] },
+        { "Name": "GuiObject", "Superclass": "Frame", "Members": [] },
+        { "Name": "Frame", "Superclass": "GuiObject", "Members": [] }], "Enums": [] }"#,
+        "$.Classes[1]: class GuiObject inherits from itself, through Frame");
+    test_load_error!(own_superclass, r#"[{ "type": "Class", "Name": "Instance", "Superclass": "Instance", "tags": [] }]"#,
+        "$[0]: class Instance inherits from itself");
+
+    #[test]
+    fn cut_off_bad_superclasses_when_tolerant() {
+        let (api, warnings) = load_api(r#"[
+            { "type": "Class", "Name": "Instance", "Superclass": null, "tags": [] },
+            { "type": "Class", "Name": "Frame", "Superclass": "GuiObject", "tags": [] },
+            { "type": "Class", "Name": "A", "Superclass": "B", "tags": [] },
+            { "type": "Class", "Name": "B", "Superclass": "A", "tags": [] },
+            { "type": "Class", "Name": "C", "Superclass": "B", "tags": [] },
+            { "type": "Property", "Class": "A", "Name": "Name", "ValueType": "string", "tags": [] }
+        ]"#, ParseMode::Tolerant).unwrap();
+
+        assert_eq!(warnings, vec![
+            "$[1]: class Frame inherits from unknown class GuiObject, skipping it",
+            "$[2]: class A inherits from itself, through B, skipping it",
+        ]);
+        assert_eq!(api.get_instance("Frame").unwrap().get_superclass(), &None);
+        assert!(api.get_property(api.get_instance("C").unwrap(), "Name").is_some());
+        assert!(api.inherits("C", "A"));
+    }
+
+    #[test]
+    fn skip_unreadable_entries_when_tolerant() {
+        let (api, warnings) = load_api(r#"[
+            { "type": "Class", "Name": "Instance", "tags": [] },
+            { "type": "Class", "tags": [] },
+            { "type": "Property", "Class": "Instance", "Name": "Name", "ValueType": "string", "tags": [] },
+            { "type": "Property", "Class": "Part", "Name": "Size", "ValueType": "Vector3", "tags": [] },
+            { "type": "Function", "Class": "Instance", "Name": "Destroy", "tags": [] }
+        ]"#, ParseMode::Tolerant).unwrap();
+
+        assert!(api.get_property(api.get_instance("Instance").unwrap(), "Name").is_some());
+        assert_eq!(warnings, vec![
+            "$[1]: missing field `Name`, skipping it",
+            "$[3]: property Size belongs to unknown class Part, skipping it",
+        ]);
+    }
+
+    #[test]
+    fn skip_unknown_members_when_tolerant() {
+        let dump = r#"{ "Classes": [{ "Name": "Instance", "Superclass": "<<<ROOT>>>", "Members": [
+            { "MemberType": "Attribute", "Name": "Archivable" }] }], "Enums": [] }"#;
+
+        assert_eq!(load_api(dump, ParseMode::Strict).err(), Some("$.Classes[0].Members[0]: unknown member type Attribute".to_owned()));
+        assert_eq!(load_api(dump, ParseMode::Tolerant).unwrap().1,
+            vec!["$.Classes[0].Members[0]: unknown member type Attribute, skipping it"]);
+    }
+}
@@ -0,0 +1,113 @@
+// The compact format build.rs turns the pinned api.json into. build.rs includes
+// this file too, so it must not depend on the rest of the crate.
+//
+// After the magic come the classes, then the enums, each list led by its
+// length. A class is its name, superclass ("" for none), tags and properties,
+// a property its name, tags, type category and type name. An enum is its name,
+// tags and items, an item its name and value. Numbers are little endian u32s
+// and strings are UTF-8 led by their length.
+
+pub const MAGIC: &[u8] = b"RGSSAPI\x01";
+
+// How a property's type name is to be read
+pub const CLASS_TYPE: u8 = 0;
+pub const ENUM_TYPE: u8 = 1;
+pub const DATA_TYPE: u8 = 2;
+
+// The category of a type the flat format names, and the name kept for it.
+// That format only has the type's name, so classes are told apart by their
+// "Class:" prefix and enums by whether an enum of that name exists.
+pub fn type_category<F: Fn(&str) -> bool>(value_type: &str, is_enum: F) -> (u8, &str) {
+    match value_type.strip_prefix("Class:") {
+        Some(class_name) => (CLASS_TYPE, class_name),
+        None if is_enum(value_type) => (ENUM_TYPE, value_type),
+        None => (DATA_TYPE, value_type),
+    }
+}
+
+// Only build.rs and the tests write snapshots
+#[allow(dead_code)]
+pub struct Writer {
+    bytes: Vec<u8>,
+}
+
+#[allow(dead_code)]
+impl Writer {
+    pub fn new() -> Writer {
+        Writer { bytes: MAGIC.to_vec() }
+    }
+
+    pub fn u8(&mut self, n: u8) {
+        self.bytes.push(n);
+    }
+
+    pub fn u32(&mut self, n: u32) {
+        self.bytes.extend_from_slice(&n.to_le_bytes());
+    }
+
+    pub fn str(&mut self, s: &str) {
+        self.u32(s.len() as u32);
+        self.bytes.extend_from_slice(s.as_bytes());
+    }
+
+    pub fn tags(&mut self, tags: &[&str]) {
+        self.u32(tags.len() as u32);
+
+        for tag in tags {
+            self.str(tag);
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Reader<'a>, String> {
+        if !bytes.starts_with(MAGIC) {
+            return Err("Not an api snapshot".to_owned());
+        }
+
+        Ok(Reader { bytes, pos: MAGIC.len() })
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self.bytes.get(self.pos..self.pos + len)
+            .ok_or_else(|| format!("Api snapshot ends early, at byte {}", self.pos))?;
+        self.pos += len;
+
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn str(&mut self) -> Result<&'a str, String> {
+        let len = self.u32()? as usize;
+        let pos = self.pos;
+
+        ::std::str::from_utf8(self.take(len)?)
+            .map_err(|_| format!("Api snapshot has a string which isn't UTF-8, at byte {}", pos))
+    }
+
+    pub fn tags(&mut self) -> Result<Vec<&'a str>, String> {
+        (0..self.u32()?).map(|_| self.str()).collect()
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+}
@@ -0,0 +1,250 @@
+extern crate serde;
+extern crate serde_json;
+
+use self::serde::Deserialize;
+use self::serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use roblox::api::*;
+
+mod api_dump;
+mod binary;
+mod snapshot;
+
+pub use self::snapshot::load_snapshot;
+
+// What to do with an entry of the dump which can't be read, such as a property
+// of a class the dump doesn't have.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ParseMode {
+    // Fail to load the whole api
+    Strict,
+    // Leave the entry out, with a warning
+    Tolerant,
+}
+
+// The warnings for the entries a tolerant parse left out.
+struct Skipped {
+    mode: ParseMode,
+    warnings: Vec<String>,
+}
+
+impl Skipped {
+    fn skip(&mut self, path: &str, problem: String) -> Result<(), String> {
+        match self.mode {
+            ParseMode::Strict => Err(format!("{}: {}", path, problem)),
+            ParseMode::Tolerant => {
+                self.warnings.push(format!("{}: {}, skipping it", path, problem));
+                Ok(())
+            }
+        }
+    }
+
+    // The entry read as T, or None if it was skipped.
+    fn read<'a, T: Deserialize<'a>>(&mut self, value: &'a Value, path: &str) -> Result<Option<T>, String> {
+        match T::deserialize(value) {
+            Ok(entry) => Ok(Some(entry)),
+            Err(e) => self.skip(path, e.to_string()).map(|_| None),
+        }
+    }
+}
+
+// Entries of the flat format other than the ones below, which rgss has no use for.
+const IGNORED_TYPES: &[&str] = &["Function", "YieldFunction", "Event", "Callback"];
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct FlatClass {
+    name: String,
+    superclass: Option<String>,
+    #[serde(rename = "tags", default)]
+    tags: HashSet<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct FlatProperty {
+    class: String,
+    name: String,
+    value_type: String,
+    #[serde(rename = "tags", default)]
+    tags: HashSet<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct FlatEnum {
+    name: String,
+    #[serde(rename = "tags", default)]
+    tags: HashSet<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct FlatEnumItem {
+    #[serde(rename = "Enum")]
+    enum_name: String,
+    name: String,
+    value: u32,
+}
+
+// The type named in a category build.rs and the flat format share, or None
+// for an unknown category.
+fn categorised_type(category: u8, type_name: &str) -> Option<PropertyType> {
+    let val_type = match category {
+        binary::CLASS_TYPE => PropertyType::Instance(type_name.to_owned()),
+        binary::ENUM_TYPE => PropertyType::Enum(type_name.to_owned()),
+        binary::DATA_TYPE => value_type(type_name).unwrap_or_else(|| PropertyType::DataType(type_name.to_owned())),
+        _ => return None,
+    };
+
+    Some(val_type)
+}
+
+fn parse_value_type(vt: &str, enum_names: &HashSet<String>) -> PropertyType {
+    let (category, type_name) = binary::type_category(vt, |name| enum_names.contains(name));
+
+    categorised_type(category, type_name).unwrap()
+}
+
+// None for types .rg has no values for.
+fn value_type(vt: &str) -> Option<PropertyType> {
+    let val_type = match vt {
+        "int" => PropertyType::Int,
+        "int64" => PropertyType::Int64,
+        "float" => PropertyType::Float,
+        "double" => PropertyType::Double,
+        "bool" => PropertyType::Boolean,
+        "Color3" => PropertyType::Color,
+        "string" => PropertyType::String,
+        "Content" => PropertyType::Content,
+        "UDim2" => PropertyType::UDim2,
+        "UDim" => PropertyType::UDim,
+        "Vector2" => PropertyType::Vector2,
+        "Vector3" => PropertyType::Vector3,
+        "Rect" | "Rect2D" => PropertyType::Rect,
+        "NumberSequence" => PropertyType::NumberSequence,
+        "ColorSequence" => PropertyType::ColorSequence,
+        "NumberRange" => PropertyType::NumberRange,
+        _ => return None,
+    };
+
+    Some(val_type)
+}
+
+// The flat array anaminus publishes. Members can come before the class or enum
+// they belong to, so those are added first.
+fn parse_flat(api: &mut RobloxApi, blobs: &[Value], class_paths: &mut HashMap<String, String>,
+    skipped: &mut Skipped) -> Result<(), String> {
+    let mut properties = vec![];
+    let mut items = vec![];
+
+    for (i, blob) in blobs.iter().enumerate() {
+        let path = format!("$[{}]", i);
+
+        match blob.get("type").and_then(|t| t.as_str()) {
+            Some("Class") => if let Some(class) = skipped.read::<FlatClass>(blob, &path)? {
+                class_paths.entry(class.name.clone()).or_insert_with(|| path.clone());
+                api.add_instance(Instance::new(class.name, class.tags, class.superclass));
+            },
+            Some("Enum") => if let Some(enm) = skipped.read::<FlatEnum>(blob, &path)? {
+                api.add_enum(Enum::new(enm.name, enm.tags));
+            },
+            Some("Property") => properties.extend(skipped.read::<FlatProperty>(blob, &path)?.map(|prop| (path, prop))),
+            Some("EnumItem") => items.extend(skipped.read::<FlatEnumItem>(blob, &path)?.map(|item| (path, item))),
+            Some(other) if IGNORED_TYPES.contains(&other) => {}
+            Some(other) => skipped.skip(&path, format!("unknown entry type {}", other))?,
+            None => skipped.skip(&path, "entry has no type".to_owned())?,
+        }
+    }
+
+    let enum_names: HashSet<String> = api.get_enums().iter().map(|enm| enm.get_name().to_owned()).collect();
+
+    for (path, prop) in properties {
+        let val_type = parse_value_type(&prop.value_type, &enum_names);
+
+        match api.get_instance_mut(&prop.class) {
+            Some(class) => class.add_property(Property::new(prop.name, prop.tags, val_type)),
+            None => skipped.skip(&path, format!("property {} belongs to unknown class {}", prop.name, prop.class))?,
+        }
+    }
+
+    for (path, item) in items {
+        match api.get_enum_mut(&item.enum_name) {
+            Some(enm) => enm.add_member(item.name, item.value),
+            None => skipped.skip(&path, format!("item {} belongs to unknown enum {}", item.name, item.enum_name))?,
+        }
+    }
+
+    Ok(())
+}
+
+// The problem with the class's superclass, if it isn't another class of the
+// dump or the class turns out to inherit from itself.
+fn superclass_problem(api: &RobloxApi, class_name: &str) -> Option<String> {
+    let superclass = api.get_instance(class_name)?.get_superclass().as_ref()?;
+
+    if api.get_instance(superclass).is_none() {
+        return Some(format!("class {} inherits from unknown class {}", class_name, superclass));
+    }
+
+    let mut chain: Vec<&str> = vec![];
+    let mut next = Some(superclass.as_str());
+
+    while let Some(name) = next {
+        if name == class_name {
+            return Some(match chain.is_empty() {
+                true => format!("class {} inherits from itself", class_name),
+                false => format!("class {} inherits from itself, through {}", class_name, chain.join(", ")),
+            });
+        }
+
+        // A loop the class is not part of, found when checking one of its classes
+        if chain.contains(&name) {
+            return None;
+        }
+
+        chain.push(name);
+        next = api.get_instance(name).and_then(|class| class.get_superclass().as_deref());
+    }
+
+    None
+}
+
+// Looking up inherited properties walks the superclasses, so each one must
+// exist and none can lead back to the class. A tolerant parse cuts the class
+// off from its superclass instead.
+fn check_superclasses(api: &mut RobloxApi, class_paths: &HashMap<String, String>, skipped: &mut Skipped) -> Result<(), String> {
+    let names: Vec<String> = api.get_instances().iter().map(|class| class.get_name().to_owned()).collect();
+
+    for name in names {
+        if let Some(problem) = superclass_problem(api, &name) {
+            skipped.skip(class_paths.get(&name).map_or("$", |path| path.as_str()), problem)?;
+            api.get_instance_mut(&name).unwrap().set_superclass(None);
+        }
+    }
+
+    Ok(())
+}
+
+// Reads either Roblox's own API-Dump.json or the flat array anaminus publishes,
+// along with a warning for every entry a tolerant parse left out. Errors name
+// the JSON path of the entry which couldn't be read.
+pub fn load_api(data: &str, mode: ParseMode) -> Result<(RobloxApi, Vec<String>), String> {
+    let mut api = RobloxApi::new();
+    let mut skipped = Skipped { mode, warnings: vec![] };
+
+    let mut class_paths = HashMap::new();
+
+    let val: Value = serde_json::from_str(data).map_err(|e| format!("Invalid JSON: {}", e))?;
+
+    match val {
+        Value::Object(_) if val.get("Classes").is_some() => api_dump::parse_dump(&mut api, &val, &mut class_paths, &mut skipped)?,
+        Value::Array(ref blobs) => parse_flat(&mut api, blobs, &mut class_paths, &mut skipped)?,
+        _ => return Err("$: expected an API dump with Classes or the flat array of entries".to_owned()),
+    }
+
+    check_superclasses(&mut api, &class_paths, &mut skipped)?;
+
+    api.index_members();
+    Ok((api, skipped.warnings))
+}
@@ -0,0 +1,161 @@
+use std::collections::HashSet;
+use roblox::api::*;
+use super::binary::Reader;
+use super::categorised_type;
+
+fn tag_set(tags: Vec<&str>) -> HashSet<String> {
+    tags.into_iter().map(|tag| tag.to_owned()).collect()
+}
+
+fn read_property(reader: &mut Reader) -> Result<Property, String> {
+    let name = reader.str()?.to_owned();
+    let tags = tag_set(reader.tags()?);
+    let category = reader.u8()?;
+    let type_name = reader.str()?;
+
+    let val_type = categorised_type(category, type_name)
+        .ok_or_else(|| format!("Property {} has unknown type category {}", name, category))?;
+
+    Ok(Property::new(name, tags, val_type))
+}
+
+fn read_class(reader: &mut Reader) -> Result<Instance, String> {
+    let name = reader.str()?.to_owned();
+    let superclass = Some(reader.str()?.to_owned()).filter(|superclass| !superclass.is_empty());
+    let mut class = Instance::new(name, tag_set(reader.tags()?), superclass);
+
+    for _ in 0..reader.u32()? {
+        class.add_property(read_property(reader)?);
+    }
+
+    Ok(class)
+}
+
+fn read_enum(reader: &mut Reader) -> Result<Enum, String> {
+    let name = reader.str()?.to_owned();
+    let mut roblox_enum = Enum::new(name, tag_set(reader.tags()?));
+
+    for _ in 0..reader.u32()? {
+        let item = reader.str()?.to_owned();
+        roblox_enum.add_member(item, reader.u32()?);
+    }
+
+    Ok(roblox_enum)
+}
+
+// Reads the api build.rs compiled from the pinned dump.
+pub fn load_snapshot(bytes: &[u8]) -> Result<RobloxApi, String> {
+    let mut reader = Reader::new(bytes)?;
+    let mut api = RobloxApi::new();
+
+    for _ in 0..reader.u32()? {
+        api.add_instance(read_class(&mut reader)?);
+    }
+
+    for _ in 0..reader.u32()? {
+        api.add_enum(read_enum(&mut reader)?);
+    }
+
+    if !reader.is_at_end() {
+        return Err("Api snapshot has bytes after its last enum".to_owned());
+    }
+
+    api.index_members();
+    Ok(api)
+}
+
+#[cfg(test)]
+mod can_load {
+    use super::*;
+    use roblox::built_in;
+    use roblox::parser::binary::{Writer, CLASS_TYPE, ENUM_TYPE, DATA_TYPE};
+    use roblox::parser::{load_api, ParseMode};
+
+    fn snapshot() -> Vec<u8> {
+        let mut writer = Writer::new();
+
+        writer.u32(2);
+        writer.str("Instance");
+        writer.str("");
+        writer.tags(&["notCreatable"]);
+        writer.u32(1);
+        writer.str("Name");
+        writer.tags(&[]);
+        writer.u8(DATA_TYPE);
+        writer.str("string");
+
+        writer.str("TextLabel");
+        writer.str("Instance");
+        writer.tags(&[]);
+        writer.u32(3);
+        for (name, category, type_name) in [("TextXAlignment", ENUM_TYPE, "TextXAlignment"),
+            ("NextSelectionUp", CLASS_TYPE, "GuiObject"), ("BorderColor", DATA_TYPE, "BrickColor")].iter() {
+            writer.str(name);
+            writer.tags(&["deprecated"]);
+            writer.u8(*category);
+            writer.str(type_name);
+        }
+
+        writer.u32(1);
+        writer.str("TextXAlignment");
+        writer.tags(&[]);
+        writer.u32(1);
+        writer.str("Center");
+        writer.u32(2);
+
+        writer.into_bytes()
+    }
+
+    #[test]
+    fn classes_and_enums() {
+        let api = load_snapshot(&snapshot()).unwrap();
+        let label = api.get_instance("TextLabel").unwrap();
+        let type_of = |name| api.get_property(label, name).unwrap().get_type();
+
+        assert_eq!(label.get_superclass(), &Some("Instance".to_owned()));
+        assert_eq!(api.get_instance("Instance").unwrap().get_superclass(), &None);
+        assert!(api.get_instance("Instance").unwrap().has_tag("notCreatable"));
+        assert!(api.get_property(label, "BorderColor").unwrap().has_tag("deprecated"));
+
+        assert_eq!(type_of("Name"), &PropertyType::String);
+        assert_eq!(type_of("TextXAlignment"), &PropertyType::Enum("TextXAlignment".to_owned()));
+        assert_eq!(type_of("NextSelectionUp"), &PropertyType::Instance("GuiObject".to_owned()));
+        assert_eq!(type_of("BorderColor"), &PropertyType::DataType("BrickColor".to_owned()));
+        assert_eq!(api.get_enum("TextXAlignment").unwrap().get_member_value("Center"), Some(2));
+    }
+
+    #[test]
+    fn reject_broken_snapshots() {
+        let bytes = snapshot();
+
+        assert!(load_snapshot(b"[{\"type\": \"Class\"}]").is_err());
+        assert!(load_snapshot(&bytes[..bytes.len() - 1]).is_err());
+        assert!(load_snapshot(&[&bytes[..], &[0]].concat()).is_err());
+    }
+
+    #[test]
+    fn built_in_api_matches_pinned_dump() {
+        let (dump, warnings) = load_api(include_str!("../../../api.json"), ParseMode::Strict).unwrap();
+        let snapshot = built_in().unwrap();
+
+        assert!(warnings.is_empty());
+        assert_eq!(snapshot.get_instances().len(), dump.get_instances().len());
+
+        for class in dump.get_instances() {
+            let built_in = snapshot.get_instance(class.get_name()).unwrap();
+
+            assert_eq!(built_in.get_superclass(), class.get_superclass());
+            assert_eq!(built_in.get_tags(), class.get_tags());
+
+            for prop in dump.get_properties(class) {
+                let built_in_prop = snapshot.get_property(built_in, prop.get_name()).unwrap();
+
+                assert_eq!(built_in_prop.get_type(), prop.get_type());
+                assert_eq!(built_in_prop.get_tags(), prop.get_tags());
+            }
+        }
+
+        let font = snapshot.get_enum("Font").unwrap();
+        assert_eq!(font.get_member_value("SourceSans"), dump.get_enum("Font").unwrap().get_member_value("SourceSans"));
+    }
+}
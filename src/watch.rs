@@ -0,0 +1,122 @@
+use std::fs;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use codegen::Format;
+use project::{self, Project, has_extension};
+use roblox::RobloxApi;
+use rg::diagnostics::MessageFormat;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// When each file of the project was last modified.
+type Snapshot = HashMap<PathBuf, SystemTime>;
+
+fn snapshot(project: &Project) -> Snapshot {
+    project.sources().iter().chain(project.stylesheets())
+        .filter_map(|path| {
+            let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?;
+            Some((path.clone(), modified))
+        })
+        .collect()
+}
+
+// Sources which were added or edited, or every source once any stylesheet
+// was added, edited or removed since they all depend on the cascade.
+fn sources_to_rebuild(before: &Snapshot, after: &Snapshot) -> Vec<PathBuf> {
+    let is_changed = |path: &PathBuf| before.get(path) != after.get(path);
+
+    let styles_changed = before.keys().chain(after.keys())
+        .filter(|path| has_extension(path, "rgss"))
+        .any(is_changed);
+
+    let mut sources: Vec<PathBuf> = after.keys()
+        .filter(|path| has_extension(path, "rg"))
+        .filter(|path| styles_changed || is_changed(path))
+        .cloned()
+        .collect();
+
+    sources.sort();
+    sources
+}
+
+fn rebuild(api: &RobloxApi, project: &Project, sources: &[PathBuf], output: Option<&Path>, format: Format,
+           message_format: MessageFormat) {
+    let styles = match project.load_styles(message_format) {
+        Ok(styles) => styles,
+        Err(error) => return eprintln!("error: {}", error),
+    };
+
+    let failures = project::build_sources(api, &styles, project, sources, output, Some(format), message_format);
+
+    // Progress goes to stderr, as stdout is all diagnostics with --message-format json
+    eprintln!("Rebuilt {} of {} files", sources.len() - failures, sources.len());
+}
+
+// Builds everything once then keeps rebuilding whatever changes, reporting
+// errors without stopping. The api is loaded by the caller only once.
+pub fn watch(api: &RobloxApi, inputs: &[PathBuf], output: Option<&Path>, format: Format,
+             message_format: MessageFormat) -> Result<(), String> {
+    let mut project = Project::collect(inputs)?;
+    let mut last_snapshot = snapshot(&project);
+
+    rebuild(api, &project, project.sources(), output, format, message_format);
+    eprintln!("Watching for changes...");
+
+    loop {
+        thread::sleep(POLL_INTERVAL);
+
+        // Collecting again picks up files created since the last poll
+        project = match Project::collect(inputs) {
+            Ok(project) => project,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                continue;
+            }
+        };
+
+        let current_snapshot = snapshot(&project);
+        let sources = sources_to_rebuild(&last_snapshot, &current_snapshot);
+
+        if !sources.is_empty() {
+            rebuild(api, &project, &sources, output, format, message_format);
+        }
+
+        last_snapshot = current_snapshot;
+    }
+}
+
+#[cfg(test)]
+mod can_detect {
+    use super::*;
+
+    fn at(seconds: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    fn files(files: &[(&str, u64)]) -> Snapshot {
+        files.iter().map(|(path, seconds)| (PathBuf::from(path), at(*seconds))).collect()
+    }
+
+    fn paths(paths: &[&str]) -> Vec<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    macro_rules! test_rebuild {
+        ($test_name: ident, $before: expr, $after: expr, $expected: expr) => {
+            #[test]
+            fn $test_name() {
+                assert_eq!(sources_to_rebuild(&files(&$before), &files(&$after)), paths(&$expected));
+            }
+        }
+    }
+
+    test_rebuild!(nothing_changed, [("a.rg", 1), ("b.rgss", 1)], [("a.rg", 1), ("b.rgss", 1)], []);
+    test_rebuild!(edited_source, [("a.rg", 1), ("b.rg", 1)], [("a.rg", 2), ("b.rg", 1)], ["a.rg"]);
+    test_rebuild!(new_source, [("a.rg", 1)], [("a.rg", 1), ("b.rg", 1)], ["b.rg"]);
+    test_rebuild!(deleted_source, [("a.rg", 1), ("b.rg", 1)], [("a.rg", 1)], []);
+    test_rebuild!(edited_stylesheet, [("a.rg", 1), ("b.rg", 1), ("c.rgss", 1)],
+        [("a.rg", 1), ("b.rg", 1), ("c.rgss", 2)], ["a.rg", "b.rg"]);
+    test_rebuild!(deleted_stylesheet, [("a.rg", 1), ("c.rgss", 1)], [("a.rg", 1)], ["a.rg"]);
+}